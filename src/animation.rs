@@ -0,0 +1,234 @@
+//! Render successive frames of a grid simulation to the terminal.
+//!
+//! Animations are opt-in, a binary only draws frames when it was started with `--animate`.
+//! The other flags are:
+//!
+//! - `--fps=<n>`: frames per second (default 4)
+//! - `--step`: wait for enter after every frame instead of sleeping
+//! - `--no-color`: don't emit ANSI color codes
+
+use std::{
+    fmt::Display,
+    io::{BufRead, Write},
+    time::Duration,
+};
+
+/// Foreground color of an overlay.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Color {
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+    White,
+    Gray,
+}
+
+impl Color {
+    fn ansi_code(self) -> u8 {
+        match self {
+            Color::Red => 31,
+            Color::Green => 32,
+            Color::Yellow => 33,
+            Color::Blue => 34,
+            Color::Magenta => 35,
+            Color::Cyan => 36,
+            Color::White => 37,
+            Color::Gray => 90,
+        }
+    }
+
+    /// `text` wrapped in the ANSI codes drawing it in this color.
+    pub fn paint(self, text: impl Display) -> String {
+        format!("\x1B[{}m{}\x1B[0m", self.ansi_code(), text)
+    }
+}
+
+/// Whether colors are enabled, i.e. the binary wasn't started with `--no-color`.
+pub fn colors_from_args() -> bool {
+    !std::env::args().any(|arg| arg == "--no-color")
+}
+
+/// A set of grid cells drawn on top of the grid.
+#[derive(Debug, Clone, Copy)]
+pub struct Overlay<'a> {
+    /// Indices into the grid data.
+    pub cells: &'a [usize],
+    /// Character drawn instead of the grid cell, `None` keeps the cell.
+    pub glyph: Option<char>,
+    pub color: Color,
+}
+
+impl<'a> Overlay<'a> {
+    pub fn new(cells: &'a [usize], glyph: Option<char>, color: Color) -> Overlay<'a> {
+        Overlay {
+            cells,
+            glyph,
+            color,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Animation {
+    enabled: bool,
+    frame_delay: Duration,
+    step_by_step: bool,
+    colors: bool,
+    frame: usize,
+}
+
+impl Default for Animation {
+    fn default() -> Self {
+        Animation {
+            enabled: false,
+            frame_delay: Duration::from_millis(250),
+            step_by_step: false,
+            colors: true,
+            frame: 0,
+        }
+    }
+}
+
+impl Animation {
+    /// The text of the current frame: a title line and the grid with the overlays on top of it.
+    fn render(
+        &self,
+        title: impl Display,
+        grid: &[u8],
+        width: usize,
+        overlays: &[Overlay],
+    ) -> String {
+        // index of the topmost overlay for every cell
+        let mut top = vec![None; grid.len()];
+        overlays
+            .iter()
+            .enumerate()
+            .for_each(|(overlay_idx, overlay)| {
+                overlay
+                    .cells
+                    .iter()
+                    .for_each(|&cell| top[cell] = Some(overlay_idx));
+            });
+
+        let mut out = String::with_capacity(grid.len() * 2);
+        out.push_str(&format!("[i] Frame {:>4}: {}\n", self.frame, title));
+
+        grid.chunks(width).enumerate().for_each(|(row_idx, row)| {
+            row.iter().enumerate().for_each(|(col_idx, &cell)| {
+                let Some(overlay) = top[row_idx * width + col_idx].map(|idx| &overlays[idx]) else {
+                    out.push(cell as char);
+                    return;
+                };
+
+                let glyph = overlay.glyph.unwrap_or(cell as char);
+                if self.colors {
+                    out.push_str(&overlay.color.paint(glyph));
+                } else {
+                    out.push(glyph);
+                }
+            });
+            out.push('\n');
+        });
+        out
+    }
+
+    /// Configure the animation from the command line arguments.
+    pub fn from_args() -> Animation {
+        let mut animation = Animation {
+            colors: colors_from_args(),
+            ..Animation::default()
+        };
+        std::env::args().skip(1).for_each(|arg| match arg.as_str() {
+            "--animate" => animation.enabled = true,
+            "--step" => animation.step_by_step = true,
+            arg => {
+                if let Some(fps) = arg.strip_prefix("--fps=") {
+                    let fps: f64 = fps.parse().expect("invalid frame rate");
+                    assert!(fps > 0.0, "frame rate must be positive");
+                    animation.frame_delay = Duration::from_secs_f64(1.0 / fps);
+                }
+            }
+        });
+        animation
+    }
+
+    /// Whether frames are drawn at all.
+    ///
+    /// Use this to skip collecting overlays when nobody is watching.
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Clear the terminal and draw the grid with the overlays on top of it.
+    ///
+    /// Later overlays are drawn over earlier ones. Does nothing if the animation is disabled.
+    pub fn frame(&mut self, title: impl Display, grid: &[u8], width: usize, overlays: &[Overlay]) {
+        if !self.enabled {
+            return;
+        }
+
+        let mut out = String::from("\x1B[2J\x1B[1;1H");
+        out.push_str(&self.render(title, grid, width, overlays));
+
+        let mut stdout = std::io::stdout().lock();
+        stdout.write_all(out.as_bytes()).unwrap();
+        stdout.flush().unwrap();
+        drop(stdout);
+
+        self.frame += 1;
+
+        if self.step_by_step {
+            println!("[i] Press enter for the next frame");
+            std::io::stdin()
+                .lock()
+                .read_line(&mut String::new())
+                .unwrap();
+        } else {
+            std::thread::sleep(self.frame_delay);
+        }
+    }
+}
+
+#[cfg(test)]
+mod animation_test {
+    use super::{Animation, Color, Overlay};
+
+    /// Two rows of three cells.
+    const GRID: &[u8] = b"abcdef";
+
+    #[test]
+    fn overlays() {
+        let animation = Animation {
+            colors: false,
+            ..Animation::default()
+        };
+        let overlays = [
+            Overlay::new(&[0, 1, 4], Some('#'), Color::Red),
+            Overlay::new(&[1, 5], None, Color::Green),
+        ];
+
+        // the later overlay keeps the grid cell and hides the earlier glyph
+        assert_eq!(
+            animation.render("title", GRID, 3, &overlays),
+            "[i] Frame    0: title\n#bc\nd#f\n"
+        );
+    }
+
+    #[test]
+    fn colors() {
+        let animation = Animation::default();
+        let overlays = [
+            Overlay::new(&[0], Some('#'), Color::Red),
+            Overlay::new(&[0, 5], None, Color::Green),
+        ];
+
+        assert_eq!(
+            animation.render("title", GRID, 3, &overlays),
+            "[i] Frame    0: title\n\x1B[32ma\x1B[0mbc\nde\x1B[32mf\x1B[0m\n"
+        );
+        assert_eq!(Color::Gray.paint('x'), "\x1B[90mx\x1B[0m");
+    }
+}
@@ -1,111 +1,16 @@
 #![allow(dead_code)]
 
-use smallvec::{smallvec, SmallVec};
+use advent_of_code_2023::interval_set::{IntervalSet, Set};
 use std::ops::Range;
 
 /// - Any source numbers that aren't mapped correspond to the same destination number.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 struct MapRange {
     src_start: u64,
     dst_start: u64,
     len: u64,
 }
 
-trait Set: Sized {
-    /// Set intersection of self with rhs, this operation is commutative.
-    fn intersect(&self, rhs: &Self) -> Option<Self>;
-
-    /// Subtract rhs from self.
-    fn subtract(&self, rhs: &Self) -> SmallVec<[Self; 2]>;
-}
-
-impl Set for Range<u64> {
-    fn intersect(&self, other: &Self) -> Option<Self> {
-        let start = self.start.max(other.start);
-        let end = self.end.min(other.end);
-
-        if start >= end {
-            None
-        } else {
-            Some(start..end)
-        }
-    }
-    fn subtract(&self, rhs: &Self) -> SmallVec<[Self; 2]> {
-        let Some(intersection) = self.intersect(rhs) else {
-            // the sets don't overlap
-            return smallvec![self.clone()];
-        };
-
-        if &intersection == self {
-            // rhs fully covers self
-            return smallvec![];
-        }
-
-        if rhs.start > self.start && rhs.end < self.end {
-            // rhs is fully contained in self
-            smallvec![self.start..rhs.start, rhs.end..self.end]
-        } else if rhs.end > self.start && rhs.start <= self.start {
-            // rhs overlaps self on the left side
-            smallvec![rhs.end..self.end]
-        } else if rhs.start < self.end && rhs.end >= self.end {
-            // rhs overlaps self on the right side
-            smallvec![self.start..rhs.start]
-        } else {
-            unreachable!(
-                "all cases should have been covered by the code above\n\
-                    \tself: {:?}\n\
-                    \trhs: {:?}\n\
-                    \tintersection: {:?}",
-                self, rhs, intersection
-            );
-        }
-    }
-}
-
-#[cfg(test)]
-mod set_test {
-    use super::{Range, Set};
-    use smallvec::{smallvec, SmallVec};
-
-    #[test]
-    fn intersect() {
-        macro_rules! test_eq {
-            ($lhs:expr, $rhs:expr => []) => {
-                assert_eq!(($lhs).intersect(&($rhs)), None);
-                assert_eq!(($rhs).intersect(&($lhs)), None);
-            };
-            ($lhs:expr, $rhs:expr => [$result:expr]) => {
-                assert_eq!(($lhs).intersect(&($rhs)), Some($result));
-                assert_eq!(($rhs).intersect(&($lhs)), Some($result));
-            };
-        }
-
-        test_eq!(0..10, 2..12 => [2..10]);
-        test_eq!(10..20, 0..10 => []);
-        test_eq!(0..1, 10..11 => []);
-    }
-
-    #[test]
-    fn subtract() {
-        macro_rules! test_eq {
-            ($base:expr, $sub:expr => [$($equals:expr),*]) => {{
-                let expected: SmallVec::<[Range<u64>; 2]> = smallvec![$($equals),*];
-                assert_eq!(($base).subtract(&($sub)), expected);
-            }};
-        }
-
-        test_eq!(1..6, 10..16 => [1..6]);
-        test_eq!(1..6, 0..3 => [3..6]);
-        test_eq!(1..6, 4..7 => [1..4]);
-        test_eq!(1..6, 3..5 => [1..3, 5..6]);
-        test_eq!(1..6, 1..6 => []);
-        test_eq!(1..6, 1..1 => [1..6]);
-        test_eq!(1..6, 1..2 => [2..6]);
-        test_eq!(1..6, 5..6 => [1..5]);
-        test_eq!(1..6, 6..6 => [1..6]);
-    }
-}
-
 impl MapRange {
     fn src_end(&self) -> u64 {
         self.src_start + self.len
@@ -120,72 +25,161 @@ impl MapRange {
         self.dst_start..self.dst_end()
     }
 
-    /// Returns: `(MappedRange, UnmappedRanges)`
-    ///
-    /// If there is an intersection with the range this maps, that part will be mapped
-    /// and the unmapped part(s) will be put into the second tuple element.
-    fn map(&self, range: &Range<u64>) -> (Option<Range<u64>>, SmallVec<[Range<u64>; 2]>) {
-        if let Some(intersection) = self.src_range().intersect(range) {
-            let mapped_start = (intersection.start + self.dst_start) - self.src_start;
-            let mapped_end = (intersection.end + self.dst_start) - self.src_start;
-            let unmapped = range.subtract(&intersection);
-            (Some(mapped_start..mapped_end), unmapped)
-        } else {
-            (None, smallvec![range.clone()])
-        }
+    /// Shift a range that lies within the source range to the destination.
+    fn map(&self, range: &Range<u64>) -> Range<u64> {
+        let mapped_start = (range.start - self.src_start) + self.dst_start;
+        let mapped_end = (range.end - self.src_start) + self.dst_start;
+        mapped_start..mapped_end
+    }
+
+    /// Shift a range that lies within the destination range back to the source.
+    fn unmap(&self, range: &Range<u64>) -> Range<u64> {
+        let unmapped_start = (range.start - self.dst_start) + self.src_start;
+        let unmapped_end = (range.end - self.dst_start) + self.src_start;
+        unmapped_start..unmapped_end
     }
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 struct Map {
     name: String,
     mappings: Vec<MapRange>,
 }
 
 impl Map {
-    /// Tries to map as much as possible from the seeds
+    /// The category this map maps from, e.g. `seed` for `seed-to-soil`.
+    fn source(&self) -> &str {
+        self.name.split("-to-").next().unwrap_or(&self.name)
+    }
+    /// The category this map maps to, e.g. `soil` for `seed-to-soil`.
+    fn target(&self) -> &str {
+        self.name.split("-to-").last().unwrap_or(&self.name)
+    }
+
+    fn map_value(&self, num: u64) -> u64 {
+        self.mappings
+            .iter()
+            .find(|mapping| mapping.src_range().contains(&num))
+            .map(|mapping| (num - mapping.src_start) + mapping.dst_start)
+            .unwrap_or(num)
+    }
+
+    /// The mappings including the identity mappings for the gaps between them,
+    /// so together they cover every number exactly once.
     ///
-    /// Returns: `(MappedSeeds, UnmappedSeeds)`
-    fn map(&self, seeds: &Range<u64>) -> Vec<Range<u64>> {
-        let mut mapped = vec![];
-        let mut unmapped = vec![seeds.clone()];
-        let mut unmapped_temp = vec![];
+    /// Overlapping mappings are clipped, the first mapping covering a number wins like in [`Map::map`].
+    fn pieces(&self) -> Vec<MapRange> {
+        let identity = |range: Range<u64>| MapRange {
+            src_start: range.start,
+            dst_start: range.start,
+            len: range.end - range.start,
+        };
+
+        let mut pieces = Vec::with_capacity(self.mappings.len() * 2 + 1);
+        let mut covered = 0;
+        for mapping in &self.mappings {
+            if mapping.src_start > covered {
+                pieces.push(identity(covered..mapping.src_start));
+            }
+            // skip the part already covered by earlier mappings
+            let skip = covered.saturating_sub(mapping.src_start).min(mapping.len);
+            if skip < mapping.len {
+                pieces.push(MapRange {
+                    src_start: mapping.src_start + skip,
+                    dst_start: mapping.dst_start + skip,
+                    len: mapping.len - skip,
+                });
+            }
+            covered = covered.max(mapping.src_end());
+        }
+        if covered < u64::MAX {
+            pieces.push(identity(covered..u64::MAX));
+        }
+        pieces
+    }
+
+    /// Map all seeds, seeds that aren't covered by any mapping keep their number.
+    fn map(&self, seeds: &IntervalSet<u64>) -> IntervalSet<u64> {
+        let mut mapped = IntervalSet::new();
+        let mut unmapped = seeds.clone();
 
         for map in &self.mappings {
-            for seed in unmapped.iter() {
-                let (mapped_range, unmapped_ranges) = map.map(seed);
+            let hit = unmapped.intersection(&IntervalSet::from(map.src_range()));
+            hit.ranges()
+                .iter()
+                .for_each(|range| mapped.insert(map.map(range)));
+            unmapped = unmapped.difference(&hit);
+        }
+
+        mapped.union(&unmapped)
+    }
 
-                if let Some(mapped_range) = mapped_range {
-                    mapped.push(mapped_range);
+    /// All numbers that are mapped into `targets`.
+    fn preimage(&self, targets: &IntervalSet<u64>) -> IntervalSet<u64> {
+        let mut sources = IntervalSet::new();
+        for piece in self.pieces() {
+            let hit = targets.intersection(&IntervalSet::from(piece.dst_range()));
+            hit.ranges()
+                .iter()
+                .for_each(|range| sources.insert(piece.unmap(range)));
+        }
+        sources
+    }
+
+    /// A single map that does the same as applying `self` and then `next`.
+    fn then(&self, next: &Map) -> Map {
+        let next_pieces = next.pieces();
+
+        // split every piece of self where its destination hits different pieces of next
+        let mut mappings = Vec::new();
+        for piece in self.pieces() {
+            let dst = piece.dst_range();
+            let first = next_pieces.partition_point(|next| next.src_end() <= dst.start);
+
+            for next_piece in next_pieces[first..]
+                .iter()
+                .take_while(|next| next.src_start < dst.end)
+            {
+                let Some(overlap) = next_piece.src_range().intersect(&dst) else {
+                    continue;
+                };
+                let src = piece.unmap(&overlap);
+                let mapped = next_piece.map(&overlap);
+
+                // identity mappings don't need to be stored
+                if src.start != mapped.start {
+                    mappings.push(MapRange {
+                        src_start: src.start,
+                        dst_start: mapped.start,
+                        len: src.end - src.start,
+                    });
                 }
-                unmapped_temp.extend(unmapped_ranges);
             }
-            unmapped.clear();
-            std::mem::swap(&mut unmapped, &mut unmapped_temp);
         }
+        mappings.sort_unstable_by_key(|r| (r.src_start, r.src_end()));
 
-        mapped.extend(unmapped);
-        mapped
+        Map {
+            name: format!("{}-to-{}", self.source(), next.target()),
+            mappings,
+        }
     }
 }
 
 #[derive(Debug, Default)]
 struct Almanac {
-    seeds: Vec<Range<u64>>,
+    seeds: IntervalSet<u64>,
     maps: Vec<Map>,
 }
 
-fn main() {
-    let challenge = advent_of_code_2023::Challenge::start(5, 2);
-
-    let almanac = {
-        let mut lines = challenge.input_lines();
+impl Almanac {
+    fn parse(input: &str) -> Almanac {
+        let mut lines = input.lines();
 
         let seeds_line = lines.next().unwrap();
         let (_, seeds_list) = seeds_line.split_once("seeds: ").unwrap();
         let mut seed_nums = seeds_list.split_whitespace();
 
-        let mut seeds = Vec::new();
+        let mut seeds = IntervalSet::new();
         loop {
             let Some(start) = seed_nums.next() else {
                 break;
@@ -193,7 +187,7 @@ fn main() {
             let start = start.parse::<u64>().unwrap();
             let len = seed_nums.next().unwrap().parse::<u64>().unwrap();
 
-            seeds.push(start..(start + len));
+            seeds.insert(start..(start + len));
         }
 
         // skip empty line
@@ -236,18 +230,235 @@ fn main() {
         }
 
         Almanac { seeds, maps }
-    };
+    }
 
-    let result = {
-        let mut ranges = almanac.seeds.clone();
-        for map in &almanac.maps {
-            ranges = ranges
-                .iter()
-                .flat_map(|range| map.map(range))
-                .collect::<Vec<_>>();
+    /// All maps collapsed into a single map from seed to location.
+    fn seed_to_location(&self) -> Map {
+        self.maps
+            .iter()
+            .skip(1)
+            .fold(self.maps[0].clone(), |acc, next| acc.then(next))
+    }
+
+    /// Follow a seed through every map.
+    ///
+    /// Returns the name of each map with the number it maps to.
+    fn trace(&self, seed: u64) -> Vec<(&str, u64)> {
+        let mut num = seed;
+        self.maps
+            .iter()
+            .map(|map| {
+                num = map.map_value(num);
+                (map.name.as_str(), num)
+            })
+            .collect()
+    }
+
+    /// Follow seed ranges through every map.
+    ///
+    /// Returns the name of each map with the numbers it maps to.
+    fn trace_range(&self, seeds: &IntervalSet<u64>) -> Vec<(&str, IntervalSet<u64>)> {
+        let mut nums = seeds.clone();
+        self.maps
+            .iter()
+            .map(|map| {
+                nums = map.map(&nums);
+                (map.name.as_str(), nums.clone())
+            })
+            .collect()
+    }
+
+    /// Follow a location backwards through every map, starting at the last one.
+    ///
+    /// Returns the name of each map with all numbers that it maps onto the path.
+    /// The numbers of the first map are all seeds (not only the planted ones) leading to the location.
+    fn trace_back(&self, location: u64) -> Vec<(&str, IntervalSet<u64>)> {
+        let mut nums = IntervalSet::from(location..location + 1);
+        self.maps
+            .iter()
+            .rev()
+            .map(|map| {
+                nums = map.preimage(&nums);
+                (map.name.as_str(), nums.clone())
+            })
+            .collect()
+    }
+
+    /// Print where the lowest location comes from.
+    fn explain(&self, location: u64) {
+        let back = self.trace_back(location);
+        let (_, seeds) = back.last().unwrap();
+        let Some(seed) = seeds.intersection(&self.seeds).min() else {
+            println!("[i] No planted seed reaches location {}", location);
+            return;
+        };
+
+        println!("[i] Seed {} reaches location {}:", seed, location);
+        let mut from = seed;
+        for (map, (_, to)) in self.maps.iter().zip(self.trace(seed)) {
+            println!(
+                "[i]   {:>11} {:>10} -> {:<11} {:>10}",
+                map.source(),
+                from,
+                map.target(),
+                to
+            );
+            from = to;
         }
-        ranges.iter().map(|r| r.start).min().unwrap()
-    };
+    }
+}
+
+fn main() {
+    let challenge = advent_of_code_2023::Challenge::start(5, 2);
+
+    let almanac = Almanac::parse(challenge.input());
+
+    let result = almanac
+        .seed_to_location()
+        .map(&almanac.seeds)
+        .min()
+        .unwrap();
+
+    if std::env::args().any(|arg| arg == "--trace") {
+        almanac.explain(result);
+    }
 
     challenge.finish(result);
 }
+
+#[cfg(test)]
+mod compose_test {
+    use super::{Almanac, IntervalSet};
+
+    const EXAMPLE: &str = "seeds: 79 14 55 13
+
+seed-to-soil map:
+50 98 2
+52 50 48
+
+soil-to-fertilizer map:
+0 15 37
+37 52 2
+39 0 15
+
+fertilizer-to-water map:
+49 53 8
+0 11 42
+42 0 7
+57 7 4
+
+water-to-light map:
+88 18 7
+18 25 70
+
+light-to-temperature map:
+45 77 23
+81 45 19
+68 64 13
+
+temperature-to-humidity map:
+0 69 1
+1 0 69
+
+humidity-to-location map:
+60 56 37
+56 93 4";
+
+    #[test]
+    fn composed_equals_stepwise() {
+        let almanac = Almanac::parse(EXAMPLE);
+        let composed = almanac.seed_to_location();
+        assert_eq!(composed.name, "seed-to-location");
+
+        for seed in 0..120 {
+            let seeds = IntervalSet::from(seed..seed + 1);
+            let stepwise = almanac
+                .maps
+                .iter()
+                .fold(seeds.clone(), |seeds, map| map.map(&seeds));
+            assert_eq!(composed.map(&seeds), stepwise, "seed {}", seed);
+        }
+
+        assert_eq!(composed.map(&almanac.seeds).min(), Some(46));
+    }
+
+    #[test]
+    fn trace() {
+        let almanac = Almanac::parse(EXAMPLE);
+
+        let values = almanac
+            .trace(79)
+            .into_iter()
+            .map(|(_, num)| num)
+            .collect::<Vec<_>>();
+        assert_eq!(values, [81, 81, 81, 74, 78, 78, 82]);
+
+        let (name, locations) = almanac.trace_range(&almanac.seeds).pop().unwrap();
+        assert_eq!(name, "humidity-to-location");
+        assert_eq!(locations.min(), Some(46));
+
+        // location 46 comes from seed 82 through the same numbers in reverse
+        let back = almanac.trace_back(46);
+        assert_eq!(back[0], ("humidity-to-location", IntervalSet::from(46..47)));
+        let (_, seeds) = back.last().unwrap();
+        assert_eq!(seeds.intersection(&almanac.seeds).min(), Some(82));
+        for (idx, (_, num)) in almanac.trace(82).into_iter().enumerate() {
+            // the numbers after map `idx` are the ones before map `idx + 1`
+            let before_next = back.iter().rev().nth(idx + 1).map(|(_, nums)| nums);
+            assert!(before_next.is_none_or(|nums| nums.contains(num)));
+        }
+    }
+
+    #[test]
+    fn overlapping_mappings() {
+        // seeds 3 and 4 are covered by both mappings, the first one wins
+        let almanac = Almanac::parse("seeds: 0 10\n\nseed-to-soil map:\n100 0 5\n200 3 5");
+        let map = &almanac.maps[0];
+
+        for seed in 0..10 {
+            let mapped = map.map(&IntervalSet::from(seed..seed + 1));
+            let value = map.map_value(seed);
+            assert_eq!(mapped, IntervalSet::from(value..value + 1));
+        }
+        assert_eq!(
+            map.map(&almanac.seeds),
+            IntervalSet::from_ranges([100..105, 202..205, 8..10])
+        );
+
+        // of the mapped seeds only 5 reaches 202, seeds 3 and 4 went to 103 and 104,
+        // while 200..203 aren't covered by any mapping and keep their numbers
+        assert_eq!(
+            map.preimage(&IntervalSet::from(200..203)),
+            IntervalSet::from_ranges([5..6, 200..203])
+        );
+        for seed in 0..10 {
+            let value = map.map_value(seed);
+            let sources = map.preimage(&IntervalSet::from(value..value + 1));
+            assert_eq!(
+                sources.intersection(&almanac.seeds),
+                IntervalSet::from(seed..seed + 1)
+            );
+        }
+    }
+
+    #[test]
+    fn preimage() {
+        let almanac = Almanac::parse(EXAMPLE);
+        let composed = almanac.seed_to_location();
+
+        // seed 82 is the one that ends up at location 46
+        let seeds = composed.preimage(&IntervalSet::from(46..47));
+        assert_eq!(
+            seeds.intersection(&almanac.seeds),
+            IntervalSet::from(82..83)
+        );
+
+        for location in 0..120 {
+            let seeds = composed.preimage(&IntervalSet::from(location..location + 1));
+            for seed in seeds.ranges().iter().flat_map(|range| range.clone()) {
+                let mapped = composed.map(&IntervalSet::from(seed..seed + 1));
+                assert_eq!(mapped.min(), Some(location));
+            }
+        }
+    }
+}
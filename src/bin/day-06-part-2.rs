@@ -1,26 +1,64 @@
 #[derive(Debug)]
 struct Race {
-    time: u64,
-    record: u64,
+    time: u128,
+    record: u128,
 }
 
 impl Race {
-    fn zero_crossings(&self) -> [f64; 2] {
-        let time = self.time as f64;
-        let record = self.record as f64;
+    fn beats_record(&self, hold: u128) -> bool {
+        // the distance only overflows for huge races, but then it beats every possible record
+        hold.checked_mul(self.time - hold)
+            .is_none_or(|dist| dist > self.record)
+    }
 
-        let root = ((time.powi(2) / 4.0) - record).sqrt();
+    /// The shortest time to hold the button that still beats the record, if there is one.
+    fn first_win(&self) -> Option<u128> {
+        // the distance peaks at holding the button for half of the time
+        let half = self.time / 2;
+        if !self.beats_record(half) {
+            return None;
+        }
 
-        [(time / 2.0) - root, (time / 2.0) + root]
-    }
+        // `hold * (time - hold) = record` has its lower root at
+        // `(time - sqrt(time² - 4 * record)) / 2`
+        let discriminant = self
+            .time
+            .checked_mul(self.time)
+            .zip(self.record.checked_mul(4))
+            .map(|(square, record)| square - record);
+
+        let mut hold = match discriminant {
+            Some(discriminant) => (self.time - discriminant.isqrt()) / 2,
+            // the square doesn't fit into u128, fall back to a binary search for the boundary
+            None => {
+                let (mut lo, mut hi) = (0, half);
+                while lo < hi {
+                    let mid = lo + (hi - lo) / 2;
+                    if self.beats_record(mid) {
+                        hi = mid;
+                    } else {
+                        lo = mid + 1;
+                    }
+                }
+                lo
+            }
+        };
 
-    fn possible_solves(&self) -> u64 {
-        let [start, end] = self.zero_crossings();
+        // rounding the square root can be off by one in both directions
+        while hold > 0 && self.beats_record(hold - 1) {
+            hold -= 1;
+        }
+        while !self.beats_record(hold) {
+            hold += 1;
+        }
 
-        let start = start.floor() as u64;
-        let end = end.ceil() as u64;
+        Some(hold)
+    }
 
-        end - start - 1
+    fn possible_solves(&self) -> u128 {
+        // the winning hold times are symmetric around `time / 2`
+        self.first_win()
+            .map_or(0, |first| self.time - 2 * first + 1)
     }
 }
 
@@ -38,7 +76,7 @@ fn main() {
             .skip(1)
             .for_each(|num| parse_buf.push_str(num));
 
-        let time = parse_buf.parse::<u64>().unwrap();
+        let time = parse_buf.parse::<u128>().unwrap();
         parse_buf.clear();
 
         lines
@@ -48,7 +86,7 @@ fn main() {
             .skip(1)
             .for_each(|num| parse_buf.push_str(num));
 
-        let record = parse_buf.parse::<u64>().unwrap();
+        let record = parse_buf.parse::<u128>().unwrap();
 
         Race { time, record }
     };
@@ -57,3 +95,50 @@ fn main() {
 
     challenge.finish(solution);
 }
+
+#[cfg(test)]
+mod race_test {
+    use super::Race;
+
+    fn brute_force(race: &Race) -> u128 {
+        (1..race.time)
+            .filter(|&hold| hold * (race.time - hold) > race.record)
+            .count() as u128
+    }
+
+    #[test]
+    fn small_races() {
+        for time in 0..64 {
+            for record in 0..=(time * time / 4 + 2) {
+                let race = Race { time, record };
+                assert_eq!(race.possible_solves(), brute_force(&race), "{:?}", race);
+            }
+        }
+    }
+
+    #[test]
+    fn huge_races() {
+        let races = [
+            Race {
+                time: u128::MAX,
+                record: u128::MAX,
+            },
+            Race {
+                time: 1 << 100,
+                record: (1 << 120) + 12345,
+            },
+            Race {
+                time: u64::MAX as u128,
+                record: (u64::MAX as u128 / 2).pow(2),
+            },
+        ];
+        for race in races {
+            let first = race.first_win().unwrap();
+            assert!(
+                race.beats_record(first) && !race.beats_record(first - 1),
+                "{:?}",
+                race
+            );
+        }
+    }
+}
@@ -1,5 +1,7 @@
 #![feature(slice_group_by)]
 
+use advent_of_code_2023::cycle::{Cycle, History};
+
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 enum Dir {
     Left,
@@ -43,78 +45,330 @@ fn least_common_multiple(nums: &[u64]) -> u64 {
     nums.iter().cloned().reduce(num_integer::lcm).unwrap()
 }
 
-fn main() {
-    let mut challenge = advent_of_code_2023::Challenge::start(8, 2);
+/// Parse the directions and the nodes sorted by name, with resolved indices.
+fn parse(input: &str) -> (Vec<Dir>, Vec<Node>) {
+    let mut lines = input.lines();
+
+    let directions = lines.next().unwrap().as_bytes();
+    let directions = directions
+        .iter()
+        .map(|&c| match c {
+            b'L' => Dir::Left,
+            b'R' => Dir::Right,
+            _ => panic!("unknown direction {}", c),
+        })
+        .collect::<Vec<_>>();
 
-    let (directions, nodes, start_nodes) = {
-        let mut lines = challenge.input_lines();
+    // skip empty line
+    let _ = lines.next().unwrap();
 
-        let directions = lines.next().unwrap().as_bytes();
-        let directions = directions
-            .iter()
-            .map(|&c| match c {
-                b'L' => Dir::Left,
-                b'R' => Dir::Right,
-                _ => panic!("unknown direction {}", c),
-            })
-            .collect::<Vec<_>>();
+    let mut nodes = lines
+        .map(|line| Node {
+            // 'HMS = (JBS, QFS)'
+            name: line[0..3].into(),
+            left: (line[7..10].into(), usize::MAX),
+            right: (line[12..15].into(), usize::MAX),
+        })
+        .collect::<Vec<_>>();
 
-        // skip empty line
-        let _ = lines.next().unwrap();
+    // sort for binary search
+    nodes.sort_unstable_by_key(|loc| loc.name);
 
-        let mut nodes = lines
-            .map(|line| Node {
-                // 'HMS = (JBS, QFS)'
-                name: line[0..3].into(),
-                left: (line[7..10].into(), usize::MAX),
-                right: (line[12..15].into(), usize::MAX),
-            })
-            .collect::<Vec<_>>();
+    // resolve location names to indices
+    let lookup = nodes.clone();
+    nodes.iter_mut().for_each(|loc| {
+        loc.left.1 = loc.left.0.position(&lookup);
+        loc.right.1 = loc.right.0.position(&lookup);
+    });
 
-        // sort for binary search
-        nodes.sort_unstable_by_key(|loc| loc.name);
+    (directions, nodes)
+}
+
+/// The steps at which a ghost stands on a `**Z` node.
+///
+/// The ghost ends up in a loop, as both its node and the position in the directions repeat.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct GhostWalk {
+    cycle: Cycle,
+    /// All steps below `prefix + len` of the cycle ending on a `**Z` node
+    z_steps: Vec<u64>,
+}
 
-        // resolve location names to indices
-        let lookup = nodes.clone();
-        nodes.iter_mut().for_each(|loc| {
-            loc.left.1 = loc.left.0.position(&lookup);
-            loc.right.1 = loc.right.0.position(&lookup);
+impl GhostWalk {
+    fn new(start: usize, nodes: &[Node], directions: &[Dir]) -> GhostWalk {
+        // the walk repeats once the ghost is at the same node at the same direction
+        let history = History::record((start, 0), |&(node, dir_idx)| {
+            let next = match directions[dir_idx] {
+                Dir::Left => nodes[node].left.1,
+                Dir::Right => nodes[node].right.1,
+            };
+            (next, (dir_idx + 1) % directions.len())
         });
 
-        // extract starting fields
-        let start_nodes = nodes
+        let z_steps = (0..history.states.len())
+            .filter(|&step| nodes[history.states[step].0].name.suffix() == b'Z')
+            .map(|step| step as u64)
+            .collect();
+
+        GhostWalk {
+            cycle: history.cycle,
+            z_steps,
+        }
+    }
+
+    /// Number of steps before the ghost is in its loop.
+    fn prefix(&self) -> u64 {
+        self.cycle.prefix as u64
+    }
+
+    fn is_on_z(&self, step: u64) -> bool {
+        let step = usize::try_from(step).expect("step doesn't fit into usize");
+        let step = self.cycle.reduce(step) as u64;
+        self.z_steps.binary_search(&step).is_ok()
+    }
+
+    /// Z steps inside the loop as `(remainder, modulus)`, valid from `prefix` on.
+    fn z_remainders(&self) -> impl Iterator<Item = (u64, u64)> + '_ {
+        self.z_steps
             .iter()
-            .filter(|loc| loc.name.suffix() == b'A')
-            .cloned()
-            .collect::<Vec<_>>();
+            .filter(|&&step| step >= self.prefix())
+            .map(|&step| (step % self.cycle.len as u64, self.cycle.len as u64))
+    }
+}
 
-        (directions, nodes, start_nodes)
-    };
-    challenge.finish_parsing();
+/// Whether `x = a.0 (mod a.1)` and `x = b.0 (mod b.1)` have a common solution.
+fn compatible(a: (u64, u64), b: (u64, u64)) -> bool {
+    a.0.abs_diff(b.0) % num_integer::gcd(a.1, b.1) == 0
+}
 
-    let steps_to_z = start_nodes
+/// Combine `x = a.0 (mod a.1)` and `x = b.0 (mod b.1)` into `x = r (mod lcm)`,
+/// the moduli don't have to be coprime.
+///
+/// `None` if there is no common solution or the result doesn't fit into a `u64`.
+fn chinese_remainder(a: (u64, u64), b: (u64, u64)) -> Option<(u64, u64)> {
+    use num_integer::Integer;
+
+    if !compatible(a, b) {
+        return None;
+    }
+
+    let (r1, m1) = (a.0 as i128, a.1 as i128);
+    let (r2, m2) = (b.0 as i128, b.1 as i128);
+    let gcd = m1.extended_gcd(&m2);
+
+    // r1 + m1 * k = r2 (mod m2) => k = (r2 - r1) / g * inverse(m1 / g) (mod m2 / g)
+    let m2_g = m2 / gcd.gcd;
+    let k = ((r2 - r1) / gcd.gcd % m2_g)
+        .checked_mul(gcd.x % m2_g)?
+        .rem_euclid(m2_g);
+
+    let lcm = m1.checked_mul(m2_g)?;
+    let r = (r1 + m1.checked_mul(k)?).rem_euclid(lcm);
+    Some((u64::try_from(r).ok()?, u64::try_from(lcm).ok()?))
+}
+
+/// Why [`first_meeting`] found no step.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum NoMeeting {
+    /// The ghosts are never on `**Z` nodes at the same time
+    Never,
+    /// The ghosts meet, but the step doesn't fit into a `u64`
+    Overflow,
+}
+
+/// First step at which all ghosts stand on `**Z` nodes.
+fn first_meeting(walks: &[GhostWalk]) -> Result<u64, NoMeeting> {
+    // before every ghost is in its loop the ghost that takes longest is still in its prefix
+    let longest = walks
         .iter()
-        .map(|current| {
-            let mut steps = 1u64;
-            let mut current = *current;
-
-            for direction in directions.iter().cycle() {
-                match direction {
-                    Dir::Left => current = nodes[current.left.1],
-                    Dir::Right => current = nodes[current.right.1],
-                }
-                if current.name.suffix() == b'Z' {
-                    break;
-                } else {
-                    steps += 1;
+        .max_by_key(|walk| walk.prefix())
+        .ok_or(NoMeeting::Never)?;
+    let early = longest
+        .z_steps
+        .iter()
+        .take_while(|&&step| step < longest.prefix())
+        .find(|&&step| walks.iter().all(|walk| walk.is_on_z(step)));
+    if let Some(&step) = early {
+        return Ok(step);
+    }
+
+    // afterwards all ghosts loop, so every combination of their remainders has to match
+    let mut overflowed = false;
+    let mut remainders = vec![(0, 1)];
+    for walk in walks {
+        let mut combined = Vec::new();
+        for &lhs in &remainders {
+            for rhs in walk.z_remainders().filter(|&rhs| compatible(lhs, rhs)) {
+                match chinese_remainder(lhs, rhs) {
+                    Some(remainder) => combined.push(remainder),
+                    None => overflowed = true,
                 }
             }
+        }
+        combined.sort_unstable();
+        combined.dedup();
+        remainders = combined;
+    }
 
-            steps
+    // smallest step of each remainder at which every ghost loops
+    let steps = remainders
+        .into_iter()
+        .map(|(r, m)| match longest.prefix().checked_sub(r) {
+            Some(missing) => missing.div_ceil(m).checked_mul(m)?.checked_add(r),
+            None => Some(r),
         })
         .collect::<Vec<_>>();
+    overflowed |= steps.contains(&None);
+
+    match steps.into_iter().flatten().min() {
+        // a remainder that overflowed might have led to an earlier step
+        _ if overflowed => Err(NoMeeting::Overflow),
+        Some(step) => Ok(step),
+        None => Err(NoMeeting::Never),
+    }
+}
+
+fn main() {
+    let mut challenge = advent_of_code_2023::Challenge::start(8, 2);
+
+    let (directions, nodes) = parse(challenge.input());
+    let start_nodes = (0..nodes.len())
+        .filter(|&idx| nodes[idx].name.suffix() == b'A')
+        .collect::<Vec<_>>();
+
+    challenge.finish_parsing();
+
+    let walks = start_nodes
+        .iter()
+        .map(|&start| GhostWalk::new(start, &nodes, &directions))
+        .collect::<Vec<_>>();
 
-    let solution = least_common_multiple(&steps_to_z);
+    // the shortcut assumes each ghost is on Z exactly at multiples of its first Z step
+    let first_z = walks
+        .iter()
+        .map(|walk| walk.z_steps.first().copied().unwrap_or(0))
+        .collect::<Vec<_>>();
+    let shortcut = least_common_multiple(&first_z);
+
+    let solution = match first_meeting(&walks) {
+        Ok(step) => {
+            if shortcut == step {
+                println!("[i] The LCM of the first Z steps is correct for this input");
+            } else {
+                println!(
+                    "[i] The LCM of the first Z steps would be wrong: {}",
+                    shortcut
+                );
+            }
+            step
+        }
+        Err(reason) => {
+            let reason = match reason {
+                NoMeeting::Never => "the ghosts never meet on Z nodes",
+                NoMeeting::Overflow => "the meeting step doesn't fit into 64 bits",
+            };
+            println!(
+                "[i] Warning: {}, falling back to the LCM of the first Z steps",
+                reason
+            );
+            shortcut
+        }
+    };
 
     challenge.finish(solution);
 }
+
+#[cfg(test)]
+mod ghost_test {
+    use super::*;
+
+    fn solve(input: &str) -> (Vec<GhostWalk>, Result<u64, NoMeeting>) {
+        let (directions, nodes) = parse(input);
+        let walks = (0..nodes.len())
+            .filter(|&idx| nodes[idx].name.suffix() == b'A')
+            .map(|start| GhostWalk::new(start, &nodes, &directions))
+            .collect::<Vec<_>>();
+        let meeting = first_meeting(&walks);
+        (walks, meeting)
+    }
+
+    #[test]
+    fn chinese_remainder() {
+        assert_eq!(super::chinese_remainder((2, 3), (3, 5)), Some((8, 15)));
+        assert_eq!(super::chinese_remainder((2, 4), (4, 6)), Some((10, 12)));
+        assert_eq!(super::chinese_remainder((1, 4), (2, 6)), None);
+        assert_eq!(super::chinese_remainder((0, 1), (3, 7)), Some((3, 7)));
+
+        // the combined modulus doesn't fit into a u64
+        let (p, q) = ((1 << 61) - 1, (1 << 31) - 1);
+        assert_eq!(super::chinese_remainder((1, p), (2, q)), None);
+        assert!(compatible((1, p), (2, q)));
+        let (r, m) = (u64::MAX - 1, u64::MAX);
+        assert_eq!(super::chinese_remainder((r, m), (0, m - 1)), None);
+    }
+
+    #[test]
+    fn example() {
+        let (_, meeting) = solve(
+            "LR\n\n\
+            11A = (11B, XXX)\n\
+            11B = (XXX, 11Z)\n\
+            11Z = (11B, XXX)\n\
+            22A = (22B, XXX)\n\
+            22B = (22C, 22C)\n\
+            22C = (22Z, 22Z)\n\
+            22Z = (22B, 22B)\n\
+            XXX = (XXX, XXX)",
+        );
+        assert_eq!(meeting, Ok(6));
+    }
+
+    #[test]
+    fn offset_loops() {
+        // Z at steps 2, 4, 6, ... and 1, 4, 7, ..., the LCM of 2 and 1 would be 2
+        let (walks, meeting) = solve(
+            "L\n\n\
+            11A = (11B, 11B)\n\
+            11B = (11Z, 11Z)\n\
+            11Z = (11B, 11B)\n\
+            22A = (22Z, 22Z)\n\
+            22B = (22C, 22C)\n\
+            22C = (22Z, 22Z)\n\
+            22Z = (22B, 22B)",
+        );
+        assert_eq!(
+            walks[1],
+            GhostWalk {
+                cycle: Cycle { prefix: 1, len: 3 },
+                z_steps: vec![1],
+            }
+        );
+        assert_eq!(meeting, Ok(4));
+    }
+
+    #[test]
+    fn never_meet() {
+        // Z at even and odd steps only
+        let (_, meeting) = solve(
+            "L\n\n\
+            11A = (11Z, 11Z)\n\
+            11B = (11Z, 11Z)\n\
+            11Z = (11B, 11B)\n\
+            22A = (22B, 22B)\n\
+            22B = (22Z, 22Z)\n\
+            22Z = (22C, 22C)\n\
+            22C = (22Z, 22Z)",
+        );
+        assert_eq!(meeting, Err(NoMeeting::Never));
+    }
+
+    #[test]
+    fn too_far() {
+        // loops of two large primes, the meeting lies beyond u64::MAX
+        let walks = [(1 << 61) - 1, (1 << 31) - 1].map(|len| GhostWalk {
+            cycle: Cycle { prefix: 0, len },
+            z_steps: vec![1 + len as u64 / 2],
+        });
+        assert_eq!(first_meeting(&walks), Err(NoMeeting::Overflow));
+    }
+}
@@ -3,14 +3,25 @@
 
 use std::fmt::Write;
 
-use smallvec::SmallVec;
+use advent_of_code_2023::{
+    animation::{colors_from_args, Color},
+    image::{self, Image, Rgb},
+    polygon::{self, Point},
+};
 
+#[derive(Clone)]
 struct Maze {
     data: Vec<u8>,
     width: usize,
 }
 
 impl Maze {
+    fn parse(input: &str) -> Maze {
+        let width = input.lines().next().unwrap().len();
+        let data = input.lines().flat_map(str::bytes).collect();
+        Maze { data, width }
+    }
+
     fn height(&self) -> usize {
         self.data.len() / self.width
     }
@@ -25,25 +36,35 @@ impl Maze {
 impl std::fmt::Display for Maze {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         (0..self.height()).for_each(|row_idx| {
-            self.row(row_idx).iter().for_each(|&b| {
-                f.write_char(match b {
-                    b'|' => '┃', // is a vertical pipe connecting north and south.
-                    b'-' => '━', // is a horizontal pipe connecting east and west.
-                    b'L' => '┗', // is a 90-degree bend connecting north and east.
-                    b'J' => '┛', // is a 90-degree bend connecting north and west.
-                    b'7' => '┓', // is a 90-degree bend connecting south and west.
-                    b'F' => '┏', // is a 90-degree bend connecting south and east.
-                    _ => b as char,
-                })
-                .unwrap()
-            });
+            self.row(row_idx)
+                .iter()
+                .for_each(|&b| f.write_char(box_char(b, true)).unwrap());
             f.write_char('\n').unwrap();
         });
         Ok(())
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+/// The box-drawing character of a pipe, with `heavy` or light lines.
+fn box_char(b: u8, heavy: bool) -> char {
+    match (b, heavy) {
+        (b'|', true) => '┃', // is a vertical pipe connecting north and south.
+        (b'-', true) => '━', // is a horizontal pipe connecting east and west.
+        (b'L', true) => '┗', // is a 90-degree bend connecting north and east.
+        (b'J', true) => '┛', // is a 90-degree bend connecting north and west.
+        (b'7', true) => '┓', // is a 90-degree bend connecting south and west.
+        (b'F', true) => '┏', // is a 90-degree bend connecting south and east.
+        (b'|', false) => '│',
+        (b'-', false) => '─',
+        (b'L', false) => '└',
+        (b'J', false) => '┘',
+        (b'7', false) => '┐',
+        (b'F', false) => '┌',
+        _ => b as char,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 enum Dir {
     Up,
     Down,
@@ -52,6 +73,15 @@ enum Dir {
 }
 
 impl Dir {
+    fn opposite(self) -> Dir {
+        match self {
+            Dir::Up => Dir::Down,
+            Dir::Down => Dir::Up,
+            Dir::Left => Dir::Right,
+            Dir::Right => Dir::Left,
+        }
+    }
+
     // Move in the current direction and set the new direction
     // based on the pipe we just moved through.
     fn next(self, pipe: u8) -> Option<Dir> {
@@ -119,24 +149,83 @@ impl State {
     }
 }
 
-fn start_states(maze: &Maze) -> [State; 2] {
-    let start = maze
-        .data
-        .iter()
-        .position(|&b| b == b'S')
-        .map(|idx| Pos { idx })
-        .unwrap();
-
-    [Dir::Up, Dir::Down, Dir::Left, Dir::Right]
-        .into_iter()
-        .filter_map(|dir| {
-            let state = State::new(start.idx, dir);
-            let _ = state.step(maze)?;
-            Some(state)
-        })
-        .collect::<SmallVec<[State; 2]>>()
-        .into_inner()
-        .unwrap()
+/// The main loop through `S`.
+struct Loop {
+    /// The pipe hidden under `S`
+    start_pipe: u8,
+    /// All tiles of the loop in walking order, starting at `S`
+    tiles: Vec<Pos>,
+}
+
+impl Loop {
+    fn find(maze: &Maze) -> Loop {
+        let start = maze
+            .data
+            .iter()
+            .position(|&b| b == b'S')
+            .map(|idx| Pos { idx })
+            .unwrap();
+
+        // pipes off the loop may point at `S` too, so only an exit whose walk
+        // leads back to `S` is part of the loop
+        let (exits, tiles) = [Dir::Up, Dir::Down, Dir::Left, Dir::Right]
+            .into_iter()
+            .find_map(|exit| {
+                // walk around until the next step would lead onto `S`
+                let mut tiles = vec![start];
+                let mut state = State::new(start.idx, exit);
+                while let Some(next) = state.step(maze) {
+                    tiles.push(next.pos);
+                    state = next;
+                }
+
+                let back = state.dir.opposite();
+                let closed = state.pos.step(state.dir, maze) == Some(start);
+                closed.then_some(([exit.min(back), exit.max(back)], tiles))
+            })
+            .expect("there has to be a loop through `S`");
+
+        let start_pipe = match exits {
+            [Dir::Up, Dir::Down] => b'|',
+            [Dir::Left, Dir::Right] => b'-',
+            [Dir::Up, Dir::Right] => b'L',
+            [Dir::Up, Dir::Left] => b'J',
+            [Dir::Down, Dir::Left] => b'7',
+            [Dir::Down, Dir::Right] => b'F',
+            _ => unreachable!("`S` can't be left and entered from {:?}", exits),
+        };
+
+        Loop { start_pipe, tiles }
+    }
+
+    fn pipe(&self, pos: Pos, maze: &Maze) -> u8 {
+        if pos == self.tiles[0] {
+            self.start_pipe
+        } else {
+            maze.data[pos.idx]
+        }
+    }
+
+    /// The tiles at which the loop turns, in walking order.
+    fn vertices<'a>(&'a self, maze: &'a Maze) -> impl Iterator<Item = Pos> + 'a {
+        self.tiles
+            .iter()
+            .copied()
+            .filter(|&pos| b"LJ7F".contains(&self.pipe(pos, maze)))
+    }
+
+    /// The corners of the loop as `(col, row)` points.
+    fn polygon(&self, maze: &Maze) -> Vec<Point> {
+        self.vertices(maze)
+            .map(|pos| ((pos.idx % maze.width) as i64, (pos.idx / maze.width) as i64))
+            .collect()
+    }
+
+    /// Number of tiles enclosed by the loop.
+    fn enclosed_tiles(&self, maze: &Maze) -> usize {
+        let vertices = self.polygon(maze);
+        polygon::interior_points(&vertices) as usize
+    }
 }
 
 fn fill_inner_fields_row(maze_row: &mut [u8]) {
@@ -156,10 +245,7 @@ fn fill_inner_fields_row(maze_row: &mut [u8]) {
         } else if *b == b'-' {
             // this piece is ignored
             continue;
-        } else if *b == b'|'
-            || *b == b'S' // TODO: This is wrong and wont work for `F-S-7`
-            || prev == Some(b'F') && *b == b'J'
-            || prev == Some(b'L') && *b == b'7'
+        } else if *b == b'|' || prev == Some(b'F') && *b == b'J' || prev == Some(b'L') && *b == b'7'
         {
             // we crossed a border
             inside = !inside;
@@ -172,45 +258,185 @@ fn fill_inner_fields_row(maze_row: &mut [u8]) {
     }
 }
 
+/// Mark every tile inside the loop with `I` and return how many there are.
+///
+/// `S` is replaced by its pipe and all pipes that are not part of the loop by ground.
+fn scanline_fill(maze: &mut Maze, main_loop: &Loop) -> usize {
+    let mut on_loop = vec![false; maze.data.len()];
+    main_loop
+        .tiles
+        .iter()
+        .for_each(|pos| on_loop[pos.idx] = true);
+
+    maze.data[main_loop.tiles[0].idx] = main_loop.start_pipe;
+    maze.data
+        .iter_mut()
+        .zip(&on_loop)
+        .filter(|&(_, &on_loop)| !on_loop)
+        .for_each(|(b, _)| *b = b'.');
+
+    // mark all inner fields
+    (0..maze.height()).for_each(|row_idx| fill_inner_fields_row(maze.row_mut(row_idx)));
+
+    // count the inner fields
+    maze.data.iter().filter(|&&b| b == b'I').count()
+}
+
+/// Draw the main loop with heavy lines and all other pipes with light lines.
+///
+/// Inside tiles are green with ground shaded as `▒`, outside tiles are gray with blank ground.
+fn render(maze: &Maze, main_loop: &Loop, inside: &[bool], colors: bool) -> String {
+    let mut on_loop = vec![false; maze.data.len()];
+    main_loop
+        .tiles
+        .iter()
+        .for_each(|pos| on_loop[pos.idx] = true);
+
+    let mut out = String::with_capacity(maze.data.len() * 4);
+    for (idx, &b) in maze.data.iter().enumerate() {
+        let (glyph, color) = if on_loop[idx] {
+            let pipe = main_loop.pipe(Pos { idx }, maze);
+            let color = if b == b'S' { Color::Red } else { Color::Yellow };
+            (box_char(pipe, true), color)
+        } else if inside[idx] {
+            let glyph = if b == b'.' { '▒' } else { box_char(b, false) };
+            (glyph, Color::Green)
+        } else {
+            let glyph = if b == b'.' { ' ' } else { box_char(b, false) };
+            (glyph, Color::Gray)
+        };
+
+        if colors {
+            out.push_str(&color.paint(glyph));
+        } else {
+            out.push(glyph);
+        }
+        if idx % maze.width == maze.width - 1 {
+            out.push('\n');
+        }
+    }
+    out
+}
+
 fn main() {
     let mut challenge = advent_of_code_2023::Challenge::start(10, 2);
 
-    let mut maze = {
-        let width = challenge.input_lines().next().unwrap().len();
-        let mut data = Vec::new();
-        challenge
-            .input_lines()
-            .for_each(|line| data.extend_from_slice(line.as_bytes()));
-        Maze { data, width }
-    };
+    let mut maze = Maze::parse(challenge.input());
     challenge.finish_parsing();
 
-    let starts = start_states(&maze);
-    let finish = starts[1].step(&maze).unwrap();
-    let mut current = starts[0].step(&maze).unwrap();
+    let main_loop = Loop::find(&maze);
+    let solution = main_loop.enclosed_tiles(&maze);
 
-    // record the path we walked
-    let mut path = vec![starts[0].pos, current.pos];
-    path.sort_unstable();
+    // cross-check with a scanline fill
+    let original = maze.clone();
+    assert_eq!(solution, scanline_fill(&mut maze, &main_loop));
 
-    // walk the path
-    while current.pos != finish.pos {
-        current = current.step(&maze).unwrap();
-        path.insert(path.binary_search(&current.pos).unwrap_err(), current.pos);
+    if std::env::args().any(|arg| arg == "--render") {
+        let inside = maze.data.iter().map(|&b| b == b'I').collect::<Vec<_>>();
+        print!(
+            "{}",
+            render(&original, &main_loop, &inside, colors_from_args())
+        );
     }
 
-    // replace all pipes that are not part of the main loop with ground
-    maze.data
-        .iter_mut()
-        .enumerate()
-        .filter(|&(idx, _)| path.binary_search(&Pos { idx }).is_err())
-        .for_each(|(_, b)| *b = b'.');
+    // export the loop with the inside tiles highlighted
+    if let Some(image_path) = image::path_from_args() {
+        let image = Image::from_grid(&maze.data, maze.width, |&b| match b {
+            b'I' => Rgb::GREEN,
+            b'.' => Rgb::BLACK,
+            _ => Rgb::WHITE,
+        });
+        image.scaled(4).save(image_path).unwrap();
+    }
 
-    // mark all inner fields
-    (0..maze.height()).for_each(|row_idx| fill_inner_fields_row(maze.row_mut(row_idx)));
+    challenge.finish(solution);
+}
 
-    // count the inner fields
-    let solution = maze.data.iter().filter(|&&b| b == b'I').count();
+#[cfg(test)]
+mod loop_test {
+    use super::*;
 
-    challenge.finish(solution);
+    #[test]
+    fn square() {
+        let maze = Maze::parse(".....\n.S-7.\n.|.|.\n.L-J.\n.....");
+        let main_loop = Loop::find(&maze);
+
+        assert_eq!(main_loop.start_pipe, b'F');
+        assert_eq!(main_loop.tiles.len(), 8);
+        assert_eq!(
+            main_loop
+                .vertices(&maze)
+                .map(|pos| pos.idx)
+                .collect::<Vec<_>>(),
+            [6, 16, 18, 8]
+        );
+        assert_eq!(main_loop.enclosed_tiles(&maze), 1);
+    }
+
+    #[test]
+    fn stray_pipes() {
+        // `|` above and `-` left of `S` point at it without being part of the loop
+        let maze = Maze::parse(".|...\n-S-7.\n.|.|.\n.L-J.\n.....");
+        let main_loop = Loop::find(&maze);
+
+        assert_eq!(main_loop.start_pipe, b'F');
+        assert_eq!(main_loop.tiles.len(), 8);
+        assert_eq!(main_loop.enclosed_tiles(&maze), 1);
+    }
+
+    #[test]
+    fn render() {
+        let original = Maze::parse("-L|F7\n7S-7|\nL|7||\n-L-J|\nL|-JF");
+        let main_loop = Loop::find(&original);
+
+        let mut maze = original.clone();
+        assert_eq!(scanline_fill(&mut maze, &main_loop), 1);
+
+        let inside = maze.data.iter().map(|&b| b == b'I').collect::<Vec<_>>();
+        assert_eq!(
+            super::render(&original, &main_loop, &inside, false),
+            "─└│┌┐\n┐┏━┓│\n└┃┐┃│\n─┗━┛│\n└│─┘┌\n"
+        );
+    }
+
+    #[test]
+    fn examples() {
+        let examples = [
+            (
+                "...........\n.S-------7.\n.|F-----7|.\n.||.....||.\n.||.....||.\n\
+                 .|L-7.F-J|.\n.|..|.|..|.\n.L--J.L--J.\n...........",
+                b'F',
+                4,
+            ),
+            (
+                "FF7FSF7F7F7F7F7F---7\nL|LJ||||||||||||F--J\nFL-7LJLJ||||||LJL-77\n\
+                 F--JF--7||LJLJ7F7FJ-\nL---JF-JLJ.||-FJLJJ7\n|F|F-JF---7F7-L7L|7|\n\
+                 |FFJF7L7F-JF7|JL---7\n7-L-JL7||F7|L7F-7F7|\nL.L7LFJ|||||FJL7||LJ\n\
+                 L7JLJL-JLJLJL--JLJ.L",
+                b'7',
+                10,
+            ),
+        ];
+
+        for (input, start_pipe, enclosed) in examples {
+            let mut maze = Maze::parse(input);
+            let main_loop = Loop::find(&maze);
+
+            assert_eq!(main_loop.start_pipe, start_pipe);
+            assert_eq!(main_loop.enclosed_tiles(&maze), enclosed);
+            assert_eq!(scanline_fill(&mut maze, &main_loop), enclosed);
+
+            let vertices = main_loop.polygon(&maze);
+            let inside = (0..maze.data.len())
+                .filter(|&idx| maze.data[idx] == b'I')
+                .collect::<Vec<_>>();
+            let located = (0..maze.data.len())
+                .filter(|&idx| {
+                    let point = ((idx % maze.width) as i64, (idx / maze.width) as i64);
+                    polygon::locate(&vertices, point) == polygon::Location::Inside
+                })
+                .collect::<Vec<_>>();
+            assert_eq!(located, inside);
+        }
+    }
 }
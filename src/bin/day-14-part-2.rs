@@ -1,6 +1,11 @@
 #![feature(is_sorted)]
 
-use std::{fmt::Write, hash::Hasher};
+use std::{fmt::Write, time::Instant};
+
+use advent_of_code_2023::{
+    animation::{Animation, Color, Overlay},
+    cycle::{Cycle, History},
+};
 
 #[derive(Clone)]
 struct Image {
@@ -97,34 +102,43 @@ impl Image {
         self.tilt_right();
     }
 
-    fn cycle_n(&mut self, cycles: usize) {
-        let mut hashes = vec![(0, self.hashed())];
+    /// Spin `cycles` times and return after how many cycles the platform repeats.
+    fn cycle_n(&mut self, cycles: usize, animation: &mut Animation) -> Cycle {
+        self.animate_frame(animation, 0);
 
-        // keep cycling until we encounter the same hash twice
-        let repeating_hash_idx = loop {
-            if hashes.len() - 1 == cycles {
-                return;
+        // only the round rocks move, so they are all we need to remember of every cycle
+        let mut platform = BitPlatform::new(self);
+        let mut frame = self.clone();
+        let mut cycle_idx = 0;
+        let history = History::record(platform.round.clone(), |round| {
+            platform.round.clone_from(round);
+            platform.cycle();
+            cycle_idx += 1;
+            if animation.is_enabled() {
+                platform.draw(&mut frame);
+                frame.animate_frame(animation, cycle_idx);
             }
-            self.cycle();
-
-            let hash = self.hashed();
-            match hashes.binary_search_by_key(&hash, |&(_, hash)| hash) {
-                Ok(idx) => break hashes[idx].0,
-                Err(idx) => hashes.insert(idx, (hashes.len(), hash)),
-            };
-        };
-
-        // after how many cycles we arrive at the same hash again
-        let cycle_len = hashes.len() - repeating_hash_idx;
-
-        // how many cycles were done before hitting the cycle start
-        let before_cycle = repeating_hash_idx;
+            platform.round.clone()
+        });
 
-        // imagine we looped enough times and only less than cycle_len cycles are left
-        let cycles_left = (cycles - before_cycle) % cycle_len;
+        platform.round.clone_from(history.nth(cycles));
+        platform.draw(self);
+        history.cycle
+    }
 
-        // do the remaining cycles
-        (0..cycles_left).for_each(|_| self.cycle());
+    fn animate_frame(&self, animation: &mut Animation, cycle: usize) {
+        if !animation.is_enabled() {
+            return;
+        }
+        let rocks = (0..self.data.len())
+            .filter(|&idx| self.data[idx] == b'O')
+            .collect::<Vec<_>>();
+        animation.frame(
+            format_args!("cycle {:>3}, weight: {}", cycle, self.weight()),
+            &self.data,
+            self.width,
+            &[Overlay::new(&rocks, None, Color::Yellow)],
+        );
     }
 
     fn move_rock(&mut self, from: usize, to: usize) {
@@ -141,14 +155,140 @@ impl Image {
             })
             .sum::<usize>() as u64
     }
+}
+
+/// The platform as one bit per tile and one word per row.
+///
+/// Rocks are moved a whole row or column at a time.
+#[derive(Clone)]
+struct BitPlatform {
+    /// Round rocks of every row, bit `n` is column `n`
+    round: Vec<u128>,
+    /// Cube rocks of every row, bit `n` is column `n`
+    cube: Vec<u128>,
+    /// Bits of all columns
+    mask: u128,
+}
+
+impl BitPlatform {
+    fn new(image: &Image) -> BitPlatform {
+        assert!(image.width <= 128, "platform too wide for bit rows");
+
+        let bits = |row: &[u8], rock: u8| {
+            row.iter()
+                .enumerate()
+                .filter(|&(_, &tile)| tile == rock)
+                .fold(0u128, |bits, (col_idx, _)| bits | 1 << col_idx)
+        };
+        let height = image.height();
+        BitPlatform {
+            round: (0..height).map(|row| bits(image.row(row), b'O')).collect(),
+            cube: (0..height).map(|row| bits(image.row(row), b'#')).collect(),
+            mask: u128::MAX >> (128 - image.width),
+        }
+    }
 
-    fn hashed(&self) -> u64 {
-        let mut hasher = std::hash::DefaultHasher::new();
-        hasher.write(&self.data);
-        hasher.finish()
+    /// Update the round rocks of an image of the same platform.
+    fn draw(&self, image: &mut Image) {
+        let width = image.width;
+        image.data.iter_mut().enumerate().for_each(|(idx, tile)| {
+            let is_rock = self.round[idx / width] & (1 << (idx % width)) != 0;
+            match (*tile, is_rock) {
+                (b'O', false) => *tile = b'.',
+                (b'.', true) => *tile = b'O',
+                _ => (),
+            }
+        });
+    }
+
+    fn tilt_up(&mut self) {
+        // move all rocks that can move by one row until none can
+        let mut moved = true;
+        while moved {
+            moved = false;
+            for row in 1..self.round.len() {
+                let moving = self.round[row] & !(self.round[row - 1] | self.cube[row - 1]);
+                if moving != 0 {
+                    self.round[row] ^= moving;
+                    self.round[row - 1] |= moving;
+                    moved = true;
+                }
+            }
+        }
+    }
+    fn tilt_down(&mut self) {
+        let mut moved = true;
+        while moved {
+            moved = false;
+            for row in (0..self.round.len() - 1).rev() {
+                let moving = self.round[row] & !(self.round[row + 1] | self.cube[row + 1]);
+                if moving != 0 {
+                    self.round[row] ^= moving;
+                    self.round[row + 1] |= moving;
+                    moved = true;
+                }
+            }
+        }
+    }
+    fn tilt_left(&mut self) {
+        for (round, &cube) in self.round.iter_mut().zip(&self.cube) {
+            loop {
+                // rocks to the right of a free tile
+                let free = !(*round | cube) & self.mask;
+                let moving = *round & (free << 1);
+                if moving == 0 {
+                    break;
+                }
+                *round ^= moving | (moving >> 1);
+            }
+        }
+    }
+    fn tilt_right(&mut self) {
+        for (round, &cube) in self.round.iter_mut().zip(&self.cube) {
+            loop {
+                // rocks to the left of a free tile
+                let free = !(*round | cube) & self.mask;
+                let moving = *round & (free >> 1);
+                if moving == 0 {
+                    break;
+                }
+                *round ^= moving | (moving << 1);
+            }
+        }
+    }
+
+    fn cycle(&mut self) {
+        self.tilt_up();
+        self.tilt_left();
+        self.tilt_down();
+        self.tilt_right();
     }
 }
 
+/// Time spin cycles on bytes and on bits.
+fn bench(image: &Image, cycles: usize) {
+    let start = Instant::now();
+    let mut bytes = image.clone();
+    (0..cycles).for_each(|_| bytes.cycle());
+    let bytes_time = start.elapsed();
+
+    let start = Instant::now();
+    let mut bits = BitPlatform::new(image);
+    (0..cycles).for_each(|_| bits.cycle());
+    let bits_time = start.elapsed();
+
+    let mut drawn = image.clone();
+    bits.draw(&mut drawn);
+    assert!(drawn.data == bytes.data, "bit platform differs from bytes");
+
+    println!(
+        "[i] {} cycles: bytes {:.3}ms, bits {:.3}ms",
+        cycles,
+        bytes_time.as_secs_f64() * 1e3,
+        bits_time.as_secs_f64() * 1e3
+    );
+}
+
 impl std::fmt::Display for Image {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let mut rows = 0..self.height();
@@ -179,7 +319,70 @@ fn main() {
     };
     challenge.finish_parsing();
 
-    image.cycle_n(1_000_000_000);
+    if std::env::args().any(|arg| arg == "--bench") {
+        bench(&image, 1000);
+    }
+
+    let mut animation = Animation::from_args();
+    let cycle = image.cycle_n(1_000_000_000, &mut animation);
+    println!(
+        "[i] The platform repeats every {} cycles after {} cycles",
+        cycle.len, cycle.prefix
+    );
 
     challenge.finish(image.weight());
 }
+
+#[cfg(test)]
+mod spin_test {
+    use super::*;
+
+    const EXAMPLE: &str = "\
+O....#....
+O.OO#....#
+.....##...
+OO.#O....O
+.O.....O#.
+O.#..O.#.#
+..O..#O..O
+.......O..
+#....###..
+#OO..#....";
+
+    fn example() -> Image {
+        Image {
+            data: EXAMPLE.lines().flat_map(str::bytes).collect(),
+            width: 10,
+        }
+    }
+
+    #[test]
+    fn cycles() {
+        let mut image = example();
+        let cycle = image.cycle_n(1_000_000_000, &mut Animation::default());
+        assert_eq!(cycle, Cycle { prefix: 3, len: 7 });
+        assert_eq!(image.weight(), 64);
+    }
+
+    #[test]
+    fn bits_match_bytes() {
+        let mut image = example();
+        let mut platform = BitPlatform::new(&image);
+
+        type Tilt<T> = fn(&mut T);
+        let tilts: [(Tilt<Image>, Tilt<BitPlatform>); 4] = [
+            (Image::tilt_up, BitPlatform::tilt_up),
+            (Image::tilt_left, BitPlatform::tilt_left),
+            (Image::tilt_down, BitPlatform::tilt_down),
+            (Image::tilt_right, BitPlatform::tilt_right),
+        ];
+        for (tilt_bytes, tilt_bits) in tilts.into_iter().cycle().take(40) {
+            tilt_bytes(&mut image);
+            tilt_bits(&mut platform);
+
+            let mut drawn = example();
+            platform.draw(&mut drawn);
+            assert_eq!(drawn.data, image.data);
+        }
+    }
+}
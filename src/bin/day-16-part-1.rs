@@ -1,5 +1,6 @@
 use std::fmt::Write;
 
+use advent_of_code_2023::animation::{Animation, Color, Overlay};
 use smallvec::{smallvec, SmallVec};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
@@ -10,6 +11,17 @@ enum Dir {
     Right,
 }
 
+impl Dir {
+    fn arrow(self) -> char {
+        match self {
+            Dir::Up => '↑',
+            Dir::Down => '↓',
+            Dir::Left => '←',
+            Dir::Right => '→',
+        }
+    }
+}
+
 #[derive(Clone)]
 struct Field {
     data: Vec<u8>,
@@ -23,6 +35,13 @@ impl Field {
     fn row(&self, row_idx: usize) -> &[u8] {
         &self.data[(row_idx * self.width)..((row_idx + 1) * self.width)]
     }
+    fn pos(&self, row: usize, col: usize) -> usize {
+        assert!(
+            row < self.height() && col < self.width,
+            "({row}, {col}) is outside the field"
+        );
+        row * self.width + col
+    }
 }
 
 impl std::fmt::Debug for Field {
@@ -47,41 +66,109 @@ impl Beam {
         Beam { pos, dir }
     }
 
-    fn step(self, field: &Field) -> SmallVec<[Self; 2]> {
-        let next_pos = match self.dir {
-            Dir::Up if self.pos >= field.width => self.pos - field.width,
-            Dir::Down if self.pos < field.data.len() - field.width => self.pos + field.width,
-            Dir::Left if self.pos % field.width != 0 => self.pos - 1,
-            Dir::Right if self.pos % field.width != field.width - 1 => self.pos + 1,
-            _ => return smallvec![],
-        };
+    /// A beam entering the tile at `row`, `col` heading `dir`, the tile itself acts on it.
+    fn entering(field: &Field, row: usize, col: usize, dir: Dir) -> Beam {
+        Beam::new(field.pos(row, col), dir)
+    }
 
-        match (self.dir, field.data[next_pos]) {
-            (Dir::Up | Dir::Down, b'|') => smallvec![Beam::new(next_pos, self.dir)],
+    fn reflected(self, tile: u8) -> SmallVec<[Self; 2]> {
+        match (self.dir, tile) {
+            (_, b'.') => smallvec![Beam::new(self.pos, self.dir)],
+
+            (Dir::Up | Dir::Down, b'|') => smallvec![Beam::new(self.pos, self.dir)],
             (Dir::Left | Dir::Right, b'|') => {
-                smallvec![Beam::new(next_pos, Dir::Up), Beam::new(next_pos, Dir::Down)]
+                smallvec![Beam::new(self.pos, Dir::Up), Beam::new(self.pos, Dir::Down)]
             }
 
             (Dir::Up | Dir::Down, b'-') => smallvec![
-                Beam::new(next_pos, Dir::Left),
-                Beam::new(next_pos, Dir::Right)
+                Beam::new(self.pos, Dir::Left),
+                Beam::new(self.pos, Dir::Right)
             ],
-            (Dir::Left | Dir::Right, b'-') => smallvec![Beam::new(next_pos, self.dir)],
+            (Dir::Left | Dir::Right, b'-') => smallvec![Beam::new(self.pos, self.dir)],
 
-            (Dir::Up, b'/') => smallvec![Beam::new(next_pos, Dir::Right)],
-            (Dir::Right, b'/') => smallvec![Beam::new(next_pos, Dir::Up)],
-            (Dir::Down, b'/') => smallvec![Beam::new(next_pos, Dir::Left)],
-            (Dir::Left, b'/') => smallvec![Beam::new(next_pos, Dir::Down)],
+            (Dir::Up, b'/') => smallvec![Beam::new(self.pos, Dir::Right)],
+            (Dir::Right, b'/') => smallvec![Beam::new(self.pos, Dir::Up)],
+            (Dir::Down, b'/') => smallvec![Beam::new(self.pos, Dir::Left)],
+            (Dir::Left, b'/') => smallvec![Beam::new(self.pos, Dir::Down)],
 
-            (Dir::Up, b'\\') => smallvec![Beam::new(next_pos, Dir::Left)],
-            (Dir::Left, b'\\') => smallvec![Beam::new(next_pos, Dir::Up)],
-            (Dir::Down, b'\\') => smallvec![Beam::new(next_pos, Dir::Right)],
-            (Dir::Right, b'\\') => smallvec![Beam::new(next_pos, Dir::Down)],
+            (Dir::Up, b'\\') => smallvec![Beam::new(self.pos, Dir::Left)],
+            (Dir::Left, b'\\') => smallvec![Beam::new(self.pos, Dir::Up)],
+            (Dir::Down, b'\\') => smallvec![Beam::new(self.pos, Dir::Right)],
+            (Dir::Right, b'\\') => smallvec![Beam::new(self.pos, Dir::Down)],
 
-            (_, b'.') => smallvec![Beam::new(next_pos, self.dir)],
             _ => unimplemented!(),
         }
     }
+
+    fn moved(self, field: &Field) -> Option<Self> {
+        match self.dir {
+            Dir::Up if self.pos >= field.width => Some(Beam::new(self.pos - field.width, self.dir)),
+            Dir::Down if self.pos < field.data.len() - field.width => {
+                Some(Beam::new(self.pos + field.width, self.dir))
+            }
+            Dir::Left if self.pos % field.width != 0 => Some(Beam::new(self.pos - 1, self.dir)),
+            Dir::Right if self.pos % field.width != field.width - 1 => {
+                Some(Beam::new(self.pos + 1, self.dir))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Show the energized tiles and the beams that are still travelling.
+fn animate_frame(animation: &mut Animation, field: &Field, seen: &[Beam], active: &[Beam]) {
+    let energized = seen.iter().map(|beam| beam.pos).collect::<Vec<_>>();
+    let heads = [Dir::Up, Dir::Down, Dir::Left, Dir::Right].map(|dir| {
+        let cells = active
+            .iter()
+            .filter(|beam| beam.dir == dir)
+            .map(|beam| beam.pos)
+            .collect::<Vec<_>>();
+        (dir, cells)
+    });
+
+    let mut overlays = vec![Overlay::new(&energized, None, Color::Yellow)];
+    overlays.extend(
+        heads
+            .iter()
+            .map(|(dir, cells)| Overlay::new(cells, Some(dir.arrow()), Color::Red)),
+    );
+
+    animation.frame(
+        format_args!("beams: {}", active.len()),
+        &field.data,
+        field.width,
+        &overlays,
+    );
+}
+
+/// Every beam state reached from `entry`, sorted by position.
+fn energize(field: &Field, entry: Beam, animation: &mut Animation) -> Vec<Beam> {
+    let mut states = vec![entry];
+
+    // similar to cycle detection
+    let mut states_seen = states.clone();
+
+    while let Some(state) = states.pop() {
+        let new_states = state
+            .reflected(field.data[state.pos])
+            .into_iter()
+            .filter_map(|beam| beam.moved(field));
+
+        // do the cycle detection
+        new_states.for_each(|new_state| {
+            if let Err(idx) = states_seen.binary_search(&new_state) {
+                states_seen.insert(idx, new_state);
+                states.push(new_state);
+            }
+        });
+
+        if animation.is_enabled() {
+            animate_frame(animation, field, &states_seen, &states);
+        }
+    }
+
+    states_seen
 }
 
 fn main() {
@@ -98,30 +185,13 @@ fn main() {
 
     challenge.finish_parsing();
 
-    let initial_state = match field.data[0] {
-        b'-' => Beam::new(0, Dir::Right),
-        b'|' => Beam::new(0, Dir::Down),
-        b'/' => Beam::new(0, Dir::Up),
-        b'\\' => Beam::new(0, Dir::Down),
-        _ => panic!(),
-    };
-
-    let mut states = vec![initial_state];
+    let mut animation = Animation::from_args();
 
-    // similar to cycle detection
-    let mut states_seen = states.clone();
-
-    while let Some(state) = states.pop() {
-        let new_states = state.step(&field);
-
-        // do the cycle detection
-        new_states.iter().for_each(|new_state| {
-            if let Err(idx) = states_seen.binary_search(new_state) {
-                states_seen.insert(idx, *new_state);
-                states.push(*new_state);
-            }
-        });
-    }
+    let states_seen = energize(
+        &field,
+        Beam::entering(&field, 0, 0, Dir::Right),
+        &mut animation,
+    );
 
     let solution = {
         let mut iter = states_seen.iter();
@@ -141,3 +211,50 @@ fn main() {
 
     challenge.finish(solution);
 }
+
+#[cfg(test)]
+mod entry_test {
+    use super::{energize, Animation, Beam, Dir, Field};
+
+    const EXAMPLE: &str = r".|...\....
+|.-.\.....
+.....|-...
+........|.
+..........
+.........\
+..../.\\..
+.-.-/..|..
+.|....-|.\
+..//.|....";
+
+    fn energized(input: &str, row: usize, col: usize, dir: Dir) -> usize {
+        let field = Field {
+            data: input.lines().flat_map(str::bytes).collect(),
+            width: input.lines().next().unwrap().len(),
+        };
+        let mut states = energize(
+            &field,
+            Beam::entering(&field, row, col, dir),
+            &mut Animation::default(),
+        );
+        states.dedup_by_key(|state| state.pos);
+        states.len()
+    }
+
+    #[test]
+    fn example() {
+        assert_eq!(energized(EXAMPLE, 0, 0, Dir::Right), 46);
+        assert_eq!(energized(EXAMPLE, 0, 3, Dir::Down), 51);
+    }
+
+    #[test]
+    fn every_tile() {
+        // the entry tile acts on the beam like any other tile
+        assert_eq!(energized("..\n..", 0, 0, Dir::Right), 2);
+        assert_eq!(energized("|.\n..", 0, 0, Dir::Right), 2);
+        assert_eq!(energized("-.\n..", 0, 0, Dir::Right), 2);
+        assert_eq!(energized("/.\n..", 0, 0, Dir::Right), 1);
+        assert_eq!(energized("\\.\n..", 0, 0, Dir::Right), 2);
+        assert_eq!(energized("..\n..", 1, 1, Dir::Up), 2);
+    }
+}
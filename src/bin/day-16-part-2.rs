@@ -1,5 +1,9 @@
 use std::fmt::Write;
 
+use advent_of_code_2023::{
+    animation::{colors_from_args, Animation, Color, Overlay},
+    image::{self, Image, Rgb},
+};
 use smallvec::{smallvec, SmallVec};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
@@ -10,6 +14,27 @@ enum Dir {
     Right,
 }
 
+impl Dir {
+    fn arrow(self) -> char {
+        match self {
+            Dir::Up => '↑',
+            Dir::Down => '↓',
+            Dir::Left => '←',
+            Dir::Right => '→',
+        }
+    }
+
+    fn parse(text: &str) -> Dir {
+        match text {
+            "up" | "U" => Dir::Up,
+            "down" | "D" => Dir::Down,
+            "left" | "L" => Dir::Left,
+            "right" | "R" => Dir::Right,
+            _ => panic!("unknown direction `{}`", text),
+        }
+    }
+}
+
 #[derive(Clone)]
 struct Field {
     data: Vec<u8>,
@@ -23,6 +48,36 @@ impl Field {
     fn row(&self, row_idx: usize) -> &[u8] {
         &self.data[(row_idx * self.width)..((row_idx + 1) * self.width)]
     }
+    fn pos(&self, row: usize, col: usize) -> usize {
+        assert!(
+            row < self.height() && col < self.width,
+            "({row}, {col}) is outside the field"
+        );
+        row * self.width + col
+    }
+
+    /// Every beam entering the field from one of its edges.
+    fn edge_entries(&self) -> Vec<Beam> {
+        let (last_row, last_col) = (self.height() - 1, self.width - 1);
+        let mut entries = Vec::new();
+        (0..self.width).for_each(|col| {
+            entries.push(Beam::entering(self, 0, col, Dir::Down));
+            entries.push(Beam::entering(self, last_row, col, Dir::Up));
+        });
+        (0..self.height()).for_each(|row| {
+            entries.push(Beam::entering(self, row, 0, Dir::Right));
+            entries.push(Beam::entering(self, row, last_col, Dir::Left));
+        });
+        entries
+    }
+
+    /// Whether a beam heading `dir` is split by the tile at `pos`.
+    fn splits(&self, pos: usize, dir: Dir) -> bool {
+        matches!(
+            (self.data[pos], dir),
+            (b'|', Dir::Left | Dir::Right) | (b'-', Dir::Up | Dir::Down)
+        )
+    }
 }
 
 impl std::fmt::Debug for Field {
@@ -56,6 +111,11 @@ impl Beam {
         Beam { pos, dir }
     }
 
+    /// A beam entering the tile at `row`, `col` heading `dir`, the tile itself acts on it.
+    fn entering(field: &Field, row: usize, col: usize, dir: Dir) -> Beam {
+        Beam::new(field.pos(row, col), dir)
+    }
+
     fn reflected(self, tile: u8) -> SmallVec<[Self; 2]> {
         match (self.dir, tile) {
             (_, b'.') => smallvec![Beam::new(self.pos, self.dir)],
@@ -98,12 +158,302 @@ impl Beam {
             _ => None,
         }
     }
+}
+
+/// A set of tiles, one bit per tile.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Tiles(Vec<u64>);
+
+impl Tiles {
+    fn new(len: usize) -> Tiles {
+        Tiles(vec![0; len.div_ceil(64)])
+    }
+    fn insert(&mut self, idx: usize) {
+        self.0[idx / 64] |= 1 << (idx % 64);
+    }
+    fn union_with(&mut self, other: &Tiles) {
+        self.0
+            .iter_mut()
+            .zip(&other.0)
+            .for_each(|(lhs, rhs)| *lhs |= rhs);
+    }
+    fn len(&self) -> usize {
+        self.0.iter().map(|word| word.count_ones() as usize).sum()
+    }
+    fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.0.len() * 64).filter(|&idx| self.0[idx / 64] & (1 << (idx % 64)) != 0)
+    }
+}
+
+/// The tiles a beam crosses until it is split or leaves the field.
+struct Segment {
+    tiles: Vec<usize>,
+    /// The splitter that ends the segment
+    splitter: Option<usize>,
+}
+
+impl Segment {
+    /// Follow a beam entering the tile at `pos` heading `dir`.
+    fn trace(field: &Field, pos: usize, dir: Dir) -> Segment {
+        let start = Beam::new(pos, dir);
+        let mut beam = start;
+        let mut tiles = Vec::new();
+        loop {
+            tiles.push(beam.pos);
+            if field.splits(beam.pos, beam.dir) {
+                return Segment {
+                    tiles,
+                    splitter: Some(beam.pos),
+                };
+            }
+
+            // without splits, a beam can only run in circles back to where it started
+            let next = beam.reflected(field.data[beam.pos])[0].moved(field);
+            match next {
+                Some(next) if next != start => beam = next,
+                _ => {
+                    return Segment {
+                        tiles,
+                        splitter: None,
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// The splitters of a field with the beams between them.
+///
+/// Splitters that send beams to each other in circles energize the same tiles,
+/// so they are merged into one component.
+struct BeamGraph {
+    /// Component of every splitter, indexed by tile
+    component: Vec<usize>,
+    /// All tiles energized once a beam is split by a splitter of the component
+    energized: Vec<Tiles>,
+}
+
+impl BeamGraph {
+    fn new(field: &Field) -> BeamGraph {
+        let splitters = (0..field.data.len())
+            .filter(|&pos| matches!(field.data[pos], b'|' | b'-'))
+            .collect::<Vec<_>>();
+        let mut node = vec![usize::MAX; field.data.len()];
+        splitters
+            .iter()
+            .enumerate()
+            .for_each(|(idx, &pos)| node[pos] = idx);
+
+        // tiles and next splitters of both beams leaving every splitter
+        let (tiles, edges): (Vec<_>, Vec<_>) = splitters
+            .iter()
+            .map(|&pos| {
+                let dirs = match field.data[pos] {
+                    b'|' => [Dir::Up, Dir::Down],
+                    _ => [Dir::Left, Dir::Right],
+                };
+
+                let mut tiles = Tiles::new(field.data.len());
+                tiles.insert(pos);
+                let mut edges = SmallVec::<[usize; 2]>::new();
+                dirs.into_iter()
+                    .filter_map(|dir| Beam::new(pos, dir).moved(field))
+                    .map(|beam| Segment::trace(field, beam.pos, beam.dir))
+                    .for_each(|segment| {
+                        segment.tiles.iter().for_each(|&tile| tiles.insert(tile));
+                        edges.extend(segment.splitter.map(|splitter| node[splitter]));
+                    });
+                (tiles, edges)
+            })
+            .unzip();
+
+        // components are found in reverse topological order, so every component
+        // can add the tiles of the components it leads to
+        let (node_component, components) = strongly_connected(&edges);
+        let mut energized: Vec<Tiles> = Vec::with_capacity(components.len());
+        for members in &components {
+            let mut component_tiles = Tiles::new(field.data.len());
+            for &member in members {
+                component_tiles.union_with(&tiles[member]);
+                for &next in &edges[member] {
+                    if let Some(next_tiles) = energized.get(node_component[next]) {
+                        component_tiles.union_with(next_tiles);
+                    }
+                }
+            }
+            energized.push(component_tiles);
+        }
+
+        let mut component = vec![usize::MAX; field.data.len()];
+        splitters
+            .iter()
+            .enumerate()
+            .for_each(|(idx, &pos)| component[pos] = node_component[idx]);
+
+        BeamGraph {
+            component,
+            energized,
+        }
+    }
+
+    /// All tiles energized by a beam entering the tile at `pos` heading `dir`.
+    fn energized(&self, field: &Field, pos: usize, dir: Dir) -> Tiles {
+        let segment = Segment::trace(field, pos, dir);
+
+        let mut energized = match segment.splitter {
+            Some(splitter) => self.energized[self.component[splitter]].clone(),
+            None => Tiles::new(field.data.len()),
+        };
+        segment
+            .tiles
+            .iter()
+            .for_each(|&tile| energized.insert(tile));
+        energized
+    }
+}
+
+/// Tarjan's algorithm, returns the component of every node and the nodes of every component.
+///
+/// Components are numbered in reverse topological order.
+fn strongly_connected(edges: &[SmallVec<[usize; 2]>]) -> (Vec<usize>, Vec<Vec<usize>>) {
+    struct Tarjan<'a> {
+        edges: &'a [SmallVec<[usize; 2]>],
+        /// Visiting order and lowest reachable visiting order of every node
+        order: Vec<Option<(usize, usize)>>,
+        next_order: usize,
+        stack: Vec<usize>,
+        on_stack: Vec<bool>,
+        component: Vec<usize>,
+        components: Vec<Vec<usize>>,
+    }
+
+    impl Tarjan<'_> {
+        fn visit(&mut self, node: usize) -> usize {
+            let order = self.next_order;
+            self.next_order += 1;
+            self.order[node] = Some((order, order));
+            self.stack.push(node);
+            self.on_stack[node] = true;
+
+            let mut low = order;
+            for idx in 0..self.edges[node].len() {
+                let next = self.edges[node][idx];
+                match self.order[next] {
+                    None => low = low.min(self.visit(next)),
+                    Some((next_order, _)) if self.on_stack[next] => low = low.min(next_order),
+                    _ => (),
+                }
+            }
+            self.order[node] = Some((order, low));
+
+            // the node is the root of a component, everything above it on the stack belongs to it
+            if low == order {
+                let mut members = Vec::new();
+                loop {
+                    let member = self.stack.pop().unwrap();
+                    self.on_stack[member] = false;
+                    self.component[member] = self.components.len();
+                    members.push(member);
+                    if member == node {
+                        break;
+                    }
+                }
+                self.components.push(members);
+            }
+            low
+        }
+    }
+
+    let mut tarjan = Tarjan {
+        edges,
+        order: vec![None; edges.len()],
+        next_order: 0,
+        stack: Vec::new(),
+        on_stack: vec![false; edges.len()],
+        component: vec![usize::MAX; edges.len()],
+        components: Vec::new(),
+    };
+    for node in 0..edges.len() {
+        if tarjan.order[node].is_none() {
+            tarjan.visit(node);
+        }
+    }
+
+    (tarjan.component, tarjan.components)
+}
+
+/// Every beam on its way into a tile after a beam entered the tile at `pos` heading `dir`.
+fn beams(field: &Field, pos: usize, dir: Dir) -> Vec<Beam> {
+    let mut seen = vec![false; field.data.len() * 4];
+    let mut beams = vec![Beam::new(pos, dir)];
+    seen[pos * 4 + dir as usize] = true;
+
+    let mut idx = 0;
+    while let Some(&beam) = beams.get(idx) {
+        for next in beam.reflected(field.data[beam.pos]) {
+            if let Some(next) = next.moved(field) {
+                if !std::mem::replace(&mut seen[next.pos * 4 + next.dir as usize], true) {
+                    beams.push(next);
+                }
+            }
+        }
+        idx += 1;
+    }
+    beams
+}
+
+/// Draw the beams on the field.
+///
+/// Empty tiles show the direction of their beam or how many beams cross them.
+fn render_beams(field: &Field, beams: &[Beam], colors: bool) -> String {
+    let mut dirs = vec![SmallVec::<[Dir; 4]>::new(); field.data.len()];
+    beams.iter().for_each(|beam| dirs[beam.pos].push(beam.dir));
+
+    let mut out = String::with_capacity(field.data.len() * 2);
+    for (pos, &tile) in field.data.iter().enumerate() {
+        let glyph = match dirs[pos][..] {
+            _ if tile != b'.' => tile as char,
+            [] => '.',
+            [dir] => dir.arrow(),
+            ref dirs => char::from_digit(dirs.len() as u32, 10).unwrap(),
+        };
+        if colors && !dirs[pos].is_empty() {
+            out.push_str(&Color::Yellow.paint(glyph));
+        } else {
+            out.push(glyph);
+        }
+        if pos % field.width == field.width - 1 {
+            out.push('\n');
+        }
+    }
+    out
+}
 
-    fn step(self, field: &Field) -> SmallVec<[Self; 2]> {
-        self.moved(field)
-            .map(|beam| beam.reflected(field.data[beam.pos]))
-            .unwrap_or(smallvec![])
+/// For every tile, by how many of the `entries` it is energized.
+fn heatmap(field: &Field, graph: &BeamGraph, entries: &[Beam]) -> Vec<u64> {
+    let mut counts = vec![0; field.data.len()];
+    entries.iter().for_each(|entry| {
+        graph
+            .energized(field, entry.pos, entry.dir)
+            .iter()
+            .for_each(|pos| counts[pos] += 1);
+    });
+    counts
+}
+
+fn render_heatmap(counts: &[u64], width: usize) -> String {
+    const RAMP: &[u8] = b" .:-=+*#%@";
+
+    let max = counts.iter().copied().max().unwrap_or(0).max(1);
+    let mut out = String::with_capacity(counts.len() + counts.len() / width);
+    for row in counts.chunks(width) {
+        row.iter().for_each(|&count| {
+            let shade = (count * (RAMP.len() as u64 - 1)).div_ceil(max);
+            out.push(RAMP[shade as usize] as char);
+        });
+        out.push('\n');
     }
+    out
 }
 
 fn main() {
@@ -122,65 +472,196 @@ fn main() {
 
     challenge.finish_parsing();
 
-    let starting_states = {
-        fn state(idx: usize, dir: Dir) -> (usize, Beam) {
-            (idx, Beam::new(idx, dir))
-        }
+    let mut animation = Animation::from_args();
 
-        let mut states = Vec::new();
-        (0..field.width).for_each(|col_idx| {
-            states.push(state(col_idx, Dir::Down));
-            states.push(state(field.data.len() - col_idx - 1, Dir::Up));
-        });
-        (0..field.height()).for_each(|row_idx| {
-            states.push(state(row_idx * field.width, Dir::Right));
-            states.push(state((row_idx + 1) * field.width - 1, Dir::Left));
-        });
-        states
-    };
+    let graph = BeamGraph::new(&field);
+
+    let starting_states = field.edge_entries();
 
     let solution = starting_states
-        .into_iter()
-        .map(|(starting_idx, starting_state)| {
-            let mut states = starting_state
-                .reflected(field.data[starting_idx])
-                .into_vec();
-
-            let mut states_seen = vec![starting_state];
-            states_seen.extend_from_slice(&states);
-
-            while let Some(state) = states.pop() {
-                let new_states = state.step(&field);
-
-                // do the cycle detection
-                new_states.iter().for_each(|new_state| {
-                    if let Err(idx) = states_seen.binary_search(new_state) {
-                        states_seen.insert(idx, *new_state);
-                        states.push(*new_state);
-                    }
-                });
+        .iter()
+        .map(|&start| {
+            let energized = graph.energized(&field, start.pos, start.dir);
+            let solution = energized.len();
+
+            if animation.is_enabled() {
+                let energized = energized.iter().collect::<Vec<_>>();
+                animation.frame(
+                    format_args!("entry {:>5}, energized: {}", start.pos, solution),
+                    &field.data,
+                    field.width,
+                    &[Overlay::new(&energized, None, Color::Yellow)],
+                );
             }
 
-            let solution = {
-                let mut iter = states_seen.iter();
-
-                let mut count = 1usize;
-                let mut last_pos = iter.next().unwrap().pos;
-
-                iter.for_each(|state| {
-                    if state.pos != last_pos {
-                        last_pos = state.pos;
-                        count += 1;
-                    }
-                });
-
-                count
-            };
-
             solution
         })
         .max()
         .unwrap();
 
+    // visualize a single beam with `--entry=<row>,<col>,<dir>`, otherwise all entries
+    let entry = std::env::args().find_map(|arg| {
+        let entry = arg.strip_prefix("--entry=")?.to_string();
+        let mut parts = entry.split(',');
+        let row = parts.next()?.parse::<usize>().ok()?;
+        let col = parts.next()?.parse::<usize>().ok()?;
+        Some(Beam::entering(&field, row, col, Dir::parse(parts.next()?)))
+    });
+    let image_path = image::path_from_args();
+
+    if let Some(entry) = entry {
+        let beams = beams(&field, entry.pos, entry.dir);
+        print!("{}", render_beams(&field, &beams, colors_from_args()));
+
+        if let Some(image_path) = image_path {
+            let mut image = Image::from_grid(&field.data, field.width, |&b| match b {
+                b'.' => Rgb::BLACK,
+                _ => Rgb::GRAY,
+            });
+            beams.iter().for_each(|beam| {
+                let color = match field.data[beam.pos] {
+                    b'.' => Rgb::YELLOW,
+                    _ => Rgb::WHITE,
+                };
+                image.set(beam.pos, color);
+            });
+            image.scaled(4).save(image_path).unwrap();
+        }
+    } else if std::env::args().any(|arg| arg == "--heatmap") || image_path.is_some() {
+        let counts = heatmap(&field, &graph, &starting_states);
+        print!("{}", render_heatmap(&counts, field.width));
+
+        if let Some(image_path) = image_path {
+            let max = counts.iter().copied().max().unwrap_or(0);
+            let image = Image::from_grid(&counts, field.width, |&count| Rgb::heat(count, 0, max));
+            image.scaled(4).save(image_path).unwrap();
+        }
+    }
+
     challenge.finish(solution);
 }
+
+#[cfg(test)]
+mod graph_test {
+    use super::*;
+
+    const EXAMPLE: &str = r".|...\....
+|.-.\.....
+.....|-...
+........|.
+..........
+.........\
+..../.\\..
+.-.-/..|..
+.|....-|.\
+..//.|....";
+
+    fn field(input: &str) -> Field {
+        Field {
+            data: input.lines().flat_map(str::bytes).collect(),
+            width: input.lines().next().unwrap().len(),
+        }
+    }
+
+    /// Simulate every beam, starting with one entering `pos` heading `dir`.
+    fn simulate(field: &Field, pos: usize, dir: Dir) -> Tiles {
+        let mut seen = vec![Beam::new(pos, dir)];
+        let mut beams = seen.clone();
+        while let Some(beam) = beams.pop() {
+            for next in beam.reflected(field.data[beam.pos]) {
+                let Some(next) = next.moved(field) else {
+                    continue;
+                };
+                if !seen.contains(&next) {
+                    seen.push(next);
+                    beams.push(next);
+                }
+            }
+        }
+
+        let mut tiles = Tiles::new(field.data.len());
+        seen.iter().for_each(|beam| tiles.insert(beam.pos));
+        tiles
+    }
+
+    #[test]
+    fn render() {
+        let field = field(EXAMPLE);
+        let expected = r">|<<<\....
+|v-.\^....
+.v...|->>>
+.v...v^.|.
+.v...v^...
+.v...v^..\
+.v../2\\..
+<->-/vv|..
+.|<<<2-|.\
+.v//.|.v..
+";
+        let expected = expected
+            .replace('>', "→")
+            .replace('<', "←")
+            .replace('^', "↑")
+            .replace('v', "↓");
+        let beams = beams(&field, 0, Dir::Right);
+        assert_eq!(render_beams(&field, &beams, false), expected);
+    }
+
+    #[test]
+    fn heatmap() {
+        let field = field(EXAMPLE);
+        let graph = BeamGraph::new(&field);
+        let entries = [
+            Beam::entering(&field, 0, 0, Dir::Right),
+            Beam::entering(&field, 0, 3, Dir::Down),
+        ];
+
+        let counts = super::heatmap(&field, &graph, &entries);
+        assert_eq!(counts.iter().filter(|&&count| count > 0).count(), 52);
+        assert_eq!(counts.iter().filter(|&&count| count == 2).count(), 45);
+        assert!(render_heatmap(&counts, field.width).starts_with("+@@@@@    \n @ + @    \n"));
+    }
+
+    #[test]
+    fn example() {
+        let field = field(EXAMPLE);
+        let graph = BeamGraph::new(&field);
+        assert_eq!(graph.energized(&field, 0, Dir::Right).len(), 46);
+        assert_eq!(graph.energized(&field, 3, Dir::Down).len(), 51);
+
+        let entries = field.edge_entries();
+        assert_eq!(entries.len(), 40);
+        let best = entries
+            .iter()
+            .map(|entry| graph.energized(&field, entry.pos, entry.dir).len())
+            .max();
+        assert_eq!(best, Some(51));
+    }
+
+    #[test]
+    fn against_simulation() {
+        let fields = [
+            EXAMPLE,
+            // splitters sending beams to each other
+            "/.-.\\\n....|\n.-..|\n\\.|./\n..-..",
+            // a loop of mirrors passing through a splitter
+            "./.\\.\n.....\n.\\-/.\n.....",
+        ];
+        for input in fields {
+            let field = field(input);
+            let graph = BeamGraph::new(&field);
+            for pos in 0..field.data.len() {
+                for dir in [Dir::Up, Dir::Down, Dir::Left, Dir::Right] {
+                    assert_eq!(
+                        graph.energized(&field, pos, dir),
+                        simulate(&field, pos, dir),
+                        "{:?} entering {} in\n{:?}",
+                        dir,
+                        pos,
+                        field
+                    );
+                }
+            }
+        }
+    }
+}
@@ -2,6 +2,10 @@
 
 use std::{collections::VecDeque, fmt::Write};
 
+use advent_of_code_2023::{
+    animation::{Animation, Color, Overlay},
+    image::{self, Image, Rgb},
+};
 use smallvec::SmallVec;
 
 #[derive(Clone)]
@@ -200,17 +204,6 @@ impl std::fmt::Debug for Dijkstra<'_> {
 }
 
 impl Dijkstra<'_> {
-    fn debug_shortest_path_to(&self, vertex: usize) -> Graph {
-        let mut data = vec![b'_'; self.graph.data.len()];
-        self.shortest_path_to(vertex)
-            .iter()
-            .for_each(|&idx| data[idx] = b'X');
-        Graph {
-            data,
-            width: self.graph.width,
-        }
-    }
-
     fn shortest_path_to(&self, vertex: usize) -> Vec<usize> {
         let Some(mut prev) = self.prev[vertex] else {
             return Vec::new();
@@ -294,23 +287,31 @@ fn main() {
 
     let dijkstraa = dijkstra(&graph, 0);
 
-    println!("{:?}\n\n", graph);
-    println!("{:?}\n\n", dijkstraa);
-    println!(
-        "{:?}\n\n",
-        dijkstraa.debug_shortest_path_to(graph.data.len() - 1)
-    );
-    println!(
-        "{:?}",
-        dijkstraa
-            .shortest_path_to(graph.data.len() - 1)
-            .into_iter()
-            .map(|idx| (idx / graph.width, idx % graph.width))
-            .collect::<Vec<_>>()
-    );
+    let path = dijkstraa.shortest_path_to(graph.data.len() - 1);
+
+    // walk along the shortest path, one tile per frame
+    let mut animation = Animation::from_args();
+    if animation.is_enabled() {
+        (1..=path.len()).for_each(|len| {
+            animation.frame(
+                format_args!("path length: {}", len),
+                &graph.data,
+                graph.width,
+                &[Overlay::new(&path[..len], None, Color::Red)],
+            );
+        });
+    }
+
+    // export the heat-loss map with the chosen path
+    if let Some(image_path) = image::path_from_args() {
+        let mut image = Image::from_grid(&graph.data, graph.width, |&b| {
+            Rgb::heat(u64::from(b - b'0'), 1, 9)
+        });
+        image.overlay(&path, Rgb::RED);
+        image.scaled(4).save(image_path).unwrap();
+    }
 
-    let solution = dijkstraa
-        .shortest_path_to(graph.data.len() - 1)
+    let solution = path
         .iter()
         .map(|&vertex| graph.number_at(vertex) as u64)
         .sum::<u64>();
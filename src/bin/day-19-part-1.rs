@@ -1,4 +1,12 @@
-use std::hash::{Hash, Hasher};
+use std::{
+    collections::HashMap,
+    fmt,
+    fs::File,
+    io::{self, BufWriter, Write},
+    ops::Range,
+};
+
+use advent_of_code_2023::interval_set::IntervalSet;
 
 #[derive(Debug, Clone, Copy)]
 struct Part {
@@ -8,7 +16,70 @@ struct Part {
     shiny: u16,
 }
 
-#[derive(Debug, Clone, Copy)]
+impl Part {
+    fn parse(line: &str) -> Part {
+        // parse `{x=787,m=2655,a=1222,s=2876}`
+        let mut properties = line[1..line.len() - 1].split(',');
+
+        Part {
+            cool: properties.next().unwrap()[2..].parse().unwrap(),
+            musical: properties.next().unwrap()[2..].parse().unwrap(),
+            aero: properties.next().unwrap()[2..].parse().unwrap(),
+            shiny: properties.next().unwrap()[2..].parse().unwrap(),
+        }
+    }
+}
+
+impl fmt::Display for Part {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{{x={},m={},a={},s={}}}",
+            self.cool, self.musical, self.aero, self.shiny
+        )
+    }
+}
+
+/// Valid values of every rating.
+const RATINGS: Range<u16> = 1..4001;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct PartRange {
+    cool: IntervalSet<u16>,
+    musical: IntervalSet<u16>,
+    aero: IntervalSet<u16>,
+    shiny: IntervalSet<u16>,
+}
+
+impl PartRange {
+    fn new() -> PartRange {
+        PartRange {
+            cool: IntervalSet::from(RATINGS),
+            musical: IntervalSet::from(RATINGS),
+            aero: IntervalSet::from(RATINGS),
+            shiny: IntervalSet::from(RATINGS),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        [&self.cool, &self.musical, &self.aero, &self.shiny]
+            .iter()
+            .any(|range| range.is_empty())
+    }
+
+    /// Split into passing and not passing part ranges `[passing, non_passing]`
+    fn split(&self, prop: Property, op: CheckOp) -> [PartRange; 2] {
+        let range = prop.get_range(self);
+        let passing_op = op.passing();
+
+        let passing = prop.set_range(self, range.intersection(&passing_op));
+        let non_passing = prop.set_range(self, range.difference(&passing_op));
+
+        [passing, non_passing]
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 enum Property {
     Cool,
     Musical,
@@ -25,9 +96,39 @@ impl Property {
             Property::Shiny => part.shiny,
         }
     }
+    fn get_range(self, part: &PartRange) -> &IntervalSet<u16> {
+        match self {
+            Property::Cool => &part.cool,
+            Property::Musical => &part.musical,
+            Property::Aero => &part.aero,
+            Property::Shiny => &part.shiny,
+        }
+    }
+    fn set_range(self, part: &PartRange, range: IntervalSet<u16>) -> PartRange {
+        let mut part = part.clone();
+        match self {
+            Property::Cool => part.cool = range,
+            Property::Musical => part.musical = range,
+            Property::Aero => part.aero = range,
+            Property::Shiny => part.shiny = range,
+        }
+        part
+    }
 }
 
-#[derive(Debug, Clone, Copy)]
+impl fmt::Display for Property {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Property::Cool => "x",
+            Property::Musical => "m",
+            Property::Aero => "a",
+            Property::Shiny => "s",
+        };
+        f.write_str(name)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 enum CheckOp {
     /// Passes every value that is strictly less than
     LessThan(u16),
@@ -43,6 +144,32 @@ impl CheckOp {
             CheckOp::GreaterThan(n) => value > n,
         }
     }
+
+    /// All values that pass this check
+    fn passing(self) -> IntervalSet<u16> {
+        match self {
+            CheckOp::LessThan(n) => IntervalSet::from(0..n),
+            CheckOp::GreaterThan(n) => IntervalSet::from((n + 1)..u16::MAX),
+        }
+    }
+}
+
+impl fmt::Display for CheckOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CheckOp::LessThan(n) => write!(f, "<{}", n),
+            CheckOp::GreaterThan(n) => write!(f, ">{}", n),
+        }
+    }
+}
+
+/// Where a part goes next.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Target {
+    Accept,
+    Reject,
+    /// Index of a workflow in `Workflows`
+    Workflow(usize),
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -52,78 +179,382 @@ struct Check {
     /// Which kind of check this is
     op: CheckOp,
     /// Next workflow, if the check passes
-    dst: &'static str,
+    dst: Target,
 }
 
-impl Check {
-    /// Check if `part` passes this check and return the next workflow if it does
-    fn passes(self, part: Part) -> Option<&'static str> {
-        let value = self.prop.get(part);
-        self.op.passes(value).then_some(self.dst)
+impl fmt::Display for Check {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.prop, self.op)
     }
 }
 
 #[derive(Debug, Clone)]
 struct Workflow {
     checks: Vec<Check>,
-    no_match: &'static str,
+    no_match: Target,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ParseError {
+    /// A workflow with this name was defined twice
+    Duplicate(&'static str),
+    /// `workflow` sends parts to `dst`, which is never defined
+    Undefined {
+        workflow: &'static str,
+        dst: &'static str,
+    },
+    /// There is no `in` workflow to start with
+    MissingStart,
+    /// Parts can be sent around in circles through this workflow
+    Cycle(&'static str),
+}
+
+/// All workflows, referring to each other by index.
+#[derive(Debug, Clone)]
+struct Workflows {
+    names: Vec<&'static str>,
+    inner: Vec<Workflow>,
+    /// Index of the `in` workflow
+    start: usize,
 }
 
-impl Workflow {
-    fn check(&self, part: Part) -> Result<&'static str, bool> {
-        for check in &self.checks {
-            match check.passes(part) {
-                Some("R") => return Err(false),
-                Some("A") => return Err(true),
-                Some(next) => return Ok(next),
-                None => continue,
+impl Workflows {
+    fn get(&self, id: usize) -> &Workflow {
+        &self.inner[id]
+    }
+    fn name(&self, id: usize) -> &'static str {
+        self.names[id]
+    }
+    fn target_name(&self, target: Target) -> &'static str {
+        match target {
+            Target::Accept => "A",
+            Target::Reject => "R",
+            Target::Workflow(id) => self.name(id),
+        }
+    }
+
+    /// Every workflow `part` passes through with the index of the check that sent it on,
+    /// `None` if no check matched. The last workflow decides whether the part is accepted.
+    fn trace(&self, part: Part) -> (Vec<(usize, Option<usize>)>, bool) {
+        let mut steps = Vec::new();
+        let mut workflow = self.start;
+        loop {
+            let rules = self.get(workflow);
+            let check_idx = rules
+                .checks
+                .iter()
+                .position(|check| check.op.passes(check.prop.get(part)));
+            steps.push((workflow, check_idx));
+
+            match check_idx.map_or(rules.no_match, |idx| rules.checks[idx].dst) {
+                Target::Accept => return (steps, true),
+                Target::Reject => return (steps, false),
+                Target::Workflow(next) => workflow = next,
             }
         }
-        match self.no_match {
-            "R" => Err(false),
-            "A" => Err(true),
-            _ => Ok(self.no_match),
+    }
+
+    /// Print the rules deciding about `part`.
+    fn explain(&self, part: Part) {
+        let (steps, accepted) = self.trace(part);
+        let verdict = if accepted { "accepted" } else { "rejected" };
+        println!("[i] Part {} is {}:", part, verdict);
+
+        for (workflow, check_idx) in steps {
+            let rules = self.get(workflow);
+            let mut line = format!("[i]   {:>4}:", self.name(workflow));
+
+            let failed = check_idx.unwrap_or(rules.checks.len());
+            rules.checks[..failed]
+                .iter()
+                .for_each(|check| line.push_str(&format!(" {} ✗,", check)));
+
+            let dst = match check_idx {
+                Some(idx) => {
+                    let check = rules.checks[idx];
+                    line.push_str(&format!(
+                        " {} ✓ ({}={})",
+                        check,
+                        check.prop,
+                        check.prop.get(part)
+                    ));
+                    check.dst
+                }
+                None => {
+                    line.push_str(" else");
+                    rules.no_match
+                }
+            };
+            println!("{} -> {}", line, self.target_name(dst));
         }
     }
+
+    /// Write all workflows as a Graphviz graph, every check is an edge.
+    fn write_dot(&self, mut w: impl Write) -> io::Result<()> {
+        writeln!(w, "digraph workflows {{")?;
+        writeln!(w, "    node [shape=box];")?;
+        writeln!(w, "    \"A\" [shape=doublecircle, color=green];")?;
+        writeln!(w, "    \"R\" [shape=doublecircle, color=red];")?;
+
+        for (id, rules) in self.inner.iter().enumerate() {
+            let name = self.name(id);
+            for (idx, check) in rules.checks.iter().enumerate() {
+                let dst = self.target_name(check.dst);
+                writeln!(
+                    w,
+                    "    \"{}\" -> \"{}\" [label=\"{}: {}\"];",
+                    name,
+                    dst,
+                    idx + 1,
+                    check
+                )?;
+            }
+            let dst = self.target_name(rules.no_match);
+            writeln!(
+                w,
+                "    \"{}\" -> \"{}\" [label=\"else\", style=dashed];",
+                name, dst
+            )?;
+        }
+
+        writeln!(w, "}}")
+    }
+
+    /// Parse workflows up to the empty line separating them from the parts.
+    fn parse(lines: impl Iterator<Item = &'static str>) -> Result<Workflows, ParseError> {
+        // split `px{a<2006:qkq,m>2090:A,rfg}` into name and rules
+        let definitions = lines
+            .take_while(|line| !line.is_empty())
+            .map(|line| {
+                let (name, rules) = line.split_once('{').unwrap();
+                (name, &rules[..rules.len() - 1])
+            })
+            .collect::<Vec<_>>();
+
+        // intern all names first, so the rules can refer to workflows defined later
+        let names = definitions
+            .iter()
+            .map(|&(name, _)| name)
+            .collect::<Vec<_>>();
+        let mut ids = HashMap::with_capacity(names.len());
+        for (id, &name) in names.iter().enumerate() {
+            if ids.insert(name, id).is_some() {
+                return Err(ParseError::Duplicate(name));
+            }
+        }
+
+        let target = |workflow: &'static str, dst: &'static str| match dst {
+            "A" => Ok(Target::Accept),
+            "R" => Ok(Target::Reject),
+            _ => match ids.get(dst) {
+                Some(&id) => Ok(Target::Workflow(id)),
+                None => Err(ParseError::Undefined { workflow, dst }),
+            },
+        };
+
+        let inner = definitions
+            .iter()
+            .map(|&(name, rules)| {
+                let mut checks = rules.split(',').collect::<Vec<_>>();
+                let no_match = target(name, checks.pop().unwrap())?;
+
+                let checks = checks
+                    .into_iter()
+                    .map(|check| {
+                        // parse `a<2006:qkq`
+                        let (check, dst) = check.split_once(':').unwrap();
+
+                        let prop = match check.as_bytes()[0] {
+                            b'x' => Property::Cool,
+                            b'm' => Property::Musical,
+                            b'a' => Property::Aero,
+                            b's' => Property::Shiny,
+                            _ => panic!(),
+                        };
+
+                        let value = check[2..].parse::<u16>().unwrap();
+                        let op = match check.as_bytes()[1] {
+                            b'<' => CheckOp::LessThan(value),
+                            b'>' => CheckOp::GreaterThan(value),
+                            _ => panic!(),
+                        };
+
+                        Ok(Check {
+                            dst: target(name, dst)?,
+                            op,
+                            prop,
+                        })
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                Ok(Workflow { checks, no_match })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let start = *ids.get("in").ok_or(ParseError::MissingStart)?;
+        let workflows = Workflows {
+            names,
+            inner,
+            start,
+        };
+        workflows.check_cycles()?;
+        Ok(workflows)
+    }
+
+    /// Make sure no workflow can be reached again from itself.
+    fn check_cycles(&self) -> Result<(), ParseError> {
+        #[derive(Clone, Copy, PartialEq, Eq)]
+        enum State {
+            Unvisited,
+            /// On the current path
+            Visiting,
+            /// All successors checked
+            Done,
+        }
+
+        fn visit(workflows: &Workflows, id: usize, states: &mut [State]) -> Result<(), ParseError> {
+            match states[id] {
+                State::Done => return Ok(()),
+                State::Visiting => return Err(ParseError::Cycle(workflows.name(id))),
+                State::Unvisited => states[id] = State::Visiting,
+            }
+
+            let workflow = workflows.get(id);
+            let targets = workflow.checks.iter().map(|check| check.dst);
+            for target in targets.chain([workflow.no_match]) {
+                if let Target::Workflow(next) = target {
+                    visit(workflows, next, states)?;
+                }
+            }
+
+            states[id] = State::Done;
+            Ok(())
+        }
+
+        let mut states = vec![State::Unvisited; self.inner.len()];
+        (0..self.inner.len()).try_for_each(|id| visit(self, id, &mut states))
+    }
 }
 
-#[derive(Debug, Clone)]
-struct Workflows {
-    inner: Vec<(u64, Workflow)>,
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Node {
+    /// Accepted (`true`) or rejected (`false`)
+    Verdict(bool),
+    /// Continue with `pass` if the property passes the check, otherwise with `fail`
+    Check {
+        prop: Property,
+        op: CheckOp,
+        pass: usize,
+        fail: usize,
+    },
 }
 
-fn hash_name(name: &str) -> u64 {
-    let mut hasher = std::hash::DefaultHasher::new();
-    name.hash(&mut hasher);
-    hasher.finish()
+/// Workflows compiled into a decision DAG.
+///
+/// Checks that can't fail or can't pass are left out and equal nodes are shared,
+/// so all workflows that always end with the same verdict become the same leaf.
+#[derive(Debug, Clone)]
+struct DecisionTree {
+    nodes: Vec<Node>,
+    root: usize,
 }
 
-impl Workflows {
-    fn new() -> Workflows {
-        Workflows { inner: Vec::new() }
-    }
+impl DecisionTree {
+    fn compile(workflows: &Workflows) -> DecisionTree {
+        struct Compiler<'a> {
+            workflows: &'a Workflows,
+            nodes: Vec<Node>,
+            ids: HashMap<Node, usize>,
+            /// Already compiled `(workflow, check index, bounds)`
+            compiled: HashMap<(usize, usize, PartRange), usize>,
+        }
 
-    fn push(&mut self, name: &'static str, workflow: Workflow) {
-        let hash = hash_name(name);
-        let idx = self
-            .inner
-            .binary_search_by_key(&hash, |&(hash, _)| hash)
-            .unwrap_err();
-        self.inner.insert(idx, (hash, workflow));
-    }
-    fn get(&self, name: &'static str) -> Option<&Workflow> {
-        self.inner
-            .binary_search_by_key(&hash_name(name), |&(hash, _)| hash)
-            .ok()
-            .map(|idx| &self.inner[idx].1)
+        impl Compiler<'_> {
+            fn node(&mut self, node: Node) -> usize {
+                match node {
+                    // both outcomes are the same, the check is irrelevant
+                    Node::Check { pass, fail, .. } if pass == fail => pass,
+                    _ => *self.ids.entry(node).or_insert_with(|| {
+                        self.nodes.push(node);
+                        self.nodes.len() - 1
+                    }),
+                }
+            }
+
+            fn destination(&mut self, dst: Target, bounds: &PartRange) -> usize {
+                match dst {
+                    Target::Accept => self.node(Node::Verdict(true)),
+                    Target::Reject => self.node(Node::Verdict(false)),
+                    Target::Workflow(id) => self.checks(id, 0, bounds),
+                }
+            }
+
+            /// Compile the checks of a workflow starting at `check_idx`,
+            /// only parts within `bounds` can get there.
+            fn checks(&mut self, workflow: usize, check_idx: usize, bounds: &PartRange) -> usize {
+                let key = (workflow, check_idx, bounds.clone());
+                if let Some(&id) = self.compiled.get(&key) {
+                    return id;
+                }
+
+                let rules = self.workflows.get(workflow);
+                let id = match rules.checks.get(check_idx) {
+                    None => self.destination(rules.no_match, bounds),
+                    Some(check) => {
+                        let [passing, non_passing] = bounds.split(check.prop, check.op);
+                        match (passing.is_empty(), non_passing.is_empty()) {
+                            (false, false) => {
+                                let pass = self.destination(check.dst, &passing);
+                                let fail = self.checks(workflow, check_idx + 1, &non_passing);
+                                self.node(Node::Check {
+                                    prop: check.prop,
+                                    op: check.op,
+                                    pass,
+                                    fail,
+                                })
+                            }
+                            (false, true) => self.destination(check.dst, &passing),
+                            _ => self.checks(workflow, check_idx + 1, &non_passing),
+                        }
+                    }
+                };
+
+                self.compiled.insert(key, id);
+                id
+            }
+        }
+
+        let mut compiler = Compiler {
+            workflows,
+            nodes: Vec::new(),
+            ids: HashMap::new(),
+            compiled: HashMap::new(),
+        };
+        let root = compiler.checks(workflows.start, 0, &PartRange::new());
+
+        DecisionTree {
+            nodes: compiler.nodes,
+            root,
+        }
     }
 
     fn is_accepted(&self, part: Part) -> bool {
-        let mut current = self.get("in").unwrap();
+        let mut node = self.root;
         loop {
-            match current.check(part) {
-                Ok(next) => current = self.get(next).unwrap(),
-                Err(accepted) => return accepted,
+            match self.nodes[node] {
+                Node::Verdict(accepted) => return accepted,
+                Node::Check {
+                    prop,
+                    op,
+                    pass,
+                    fail,
+                } => {
+                    node = if op.passes(prop.get(part)) {
+                        pass
+                    } else {
+                        fail
+                    }
+                }
             }
         }
     }
@@ -135,68 +566,102 @@ fn main() {
     let (workflows, parts) = {
         let mut lines = challenge.input_lines();
 
-        let mut workflows = Workflows::new();
-        for line in lines.by_ref() {
-            if line.is_empty() {
-                // workflows and parts are separated by an empty line
-                break;
-            }
-
-            // parse `px{a<2006:qkq,m>2090:A,rfg}`
-            let (name, line) = line.split_once('{').unwrap();
-            let mut checks = line[..line.len() - 1].split(',').collect::<Vec<_>>();
-            let no_match = checks.pop().unwrap();
+        let workflows = Workflows::parse(lines.by_ref()).expect("invalid workflows");
 
-            let checks = checks
-                .into_iter()
-                .map(|check| {
-                    // parse `a<2006:qkq`
-                    let (check, dst) = check.split_once(':').unwrap();
-
-                    let prop = match check.as_bytes()[0] {
-                        b'x' => Property::Cool,
-                        b'm' => Property::Musical,
-                        b'a' => Property::Aero,
-                        b's' => Property::Shiny,
-                        _ => panic!(),
-                    };
-
-                    let value = check[2..].parse::<u16>().unwrap();
-                    let op = match check.as_bytes()[1] {
-                        b'<' => CheckOp::LessThan(value),
-                        b'>' => CheckOp::GreaterThan(value),
-                        _ => panic!(),
-                    };
-
-                    Check { dst, op, prop }
-                })
-                .collect::<Vec<_>>();
-
-            workflows.push(name, Workflow { checks, no_match });
-        }
-
-        let mut parts = Vec::new();
-        for line in lines {
-            // parse `{x=787,m=2655,a=1222,s=2876}`
-            let mut properties = line[1..line.len() - 1].split(',');
-
-            parts.push(Part {
-                cool: properties.next().unwrap()[2..].parse().unwrap(),
-                musical: properties.next().unwrap()[2..].parse().unwrap(),
-                aero: properties.next().unwrap()[2..].parse().unwrap(),
-                shiny: properties.next().unwrap()[2..].parse().unwrap(),
-            })
-        }
+        let parts = lines.map(Part::parse).collect::<Vec<_>>();
 
         (workflows, parts)
     };
     challenge.finish_parsing();
 
+    let tree = DecisionTree::compile(&workflows);
     let solution = parts
         .iter()
-        .filter(|&&part| workflows.is_accepted(part))
+        .filter(|&&part| tree.is_accepted(part))
         .map(|part| part.cool as u64 + part.musical as u64 + part.aero as u64 + part.shiny as u64)
         .sum::<u64>();
 
+    if let Some(path) =
+        std::env::args().find_map(|arg| arg.strip_prefix("--dot=").map(String::from))
+    {
+        let file = File::create(&path).expect("failed to create graph file");
+        workflows
+            .write_dot(BufWriter::new(file))
+            .expect("failed to write graph");
+    }
+    // explain a single part with `--explain={x=787,m=2655,a=1222,s=2876}`
+    if let Some(part) =
+        std::env::args().find_map(|arg| arg.strip_prefix("--explain=").map(Part::parse))
+    {
+        workflows.explain(part);
+    }
+
     challenge.finish(solution);
 }
+
+#[cfg(test)]
+mod explain_test {
+    use super::*;
+
+    const EXAMPLE: &str = "\
+px{a<2006:qkq,m>2090:A,rfg}
+pv{a>1716:R,A}
+lnx{m>1548:A,A}
+rfg{s<537:gd,x>2440:R,A}
+qs{s>3448:A,lnx}
+qkq{x<1416:A,crn}
+crn{x>2662:A,R}
+in{s<1351:px,qqz}
+qqz{s>2770:qs,m<1801:hdj,R}
+gd{a>3333:R,R}
+hdj{m>838:A,pv}
+";
+
+    #[test]
+    fn trace() {
+        let workflows = Workflows::parse(EXAMPLE.lines()).unwrap();
+        let names = |steps: Vec<(usize, Option<usize>)>| {
+            steps
+                .into_iter()
+                .map(|(id, check_idx)| (workflows.name(id), check_idx))
+                .collect::<Vec<_>>()
+        };
+
+        let (steps, accepted) = workflows.trace(Part::parse("{x=787,m=2655,a=1222,s=2876}"));
+        assert!(accepted);
+        assert_eq!(
+            names(steps),
+            [
+                ("in", None),
+                ("qqz", Some(0)),
+                ("qs", None),
+                ("lnx", Some(0))
+            ]
+        );
+
+        let (steps, accepted) = workflows.trace(Part::parse("{x=1679,m=44,a=2067,s=496}"));
+        assert!(!accepted);
+        assert_eq!(
+            names(steps),
+            [
+                ("in", Some(0)),
+                ("px", None),
+                ("rfg", Some(0)),
+                ("gd", None)
+            ]
+        );
+    }
+
+    #[test]
+    fn dot() {
+        let workflows = Workflows::parse(["in{x<10:a,R}", "a{A}"].into_iter()).unwrap();
+        let mut dot = Vec::new();
+        workflows.write_dot(&mut dot).unwrap();
+
+        let dot = String::from_utf8(dot).unwrap();
+        assert!(dot.starts_with("digraph workflows {"));
+        assert!(dot.contains("\"in\" -> \"a\" [label=\"1: x<10\"];"));
+        assert!(dot.contains("\"in\" -> \"R\" [label=\"else\", style=dashed];"));
+        assert!(dot.contains("\"a\" -> \"A\" [label=\"else\", style=dashed];"));
+    }
+}
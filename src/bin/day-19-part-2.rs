@@ -1,28 +1,110 @@
-use std::{
-    hash::{Hash, Hasher},
-    ops::Range,
-};
+use std::{collections::HashMap, fmt, ops::Range};
 
-#[derive(Debug, Clone)]
+use advent_of_code_2023::interval_set::IntervalSet;
+
+/// Valid values of every rating.
+const RATINGS: Range<u16> = 1..4001;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 struct PartRange {
-    cool: Option<Range<u16>>,
-    musical: Option<Range<u16>>,
-    aero: Option<Range<u16>>,
-    shiny: Option<Range<u16>>,
+    cool: IntervalSet<u16>,
+    musical: IntervalSet<u16>,
+    aero: IntervalSet<u16>,
+    shiny: IntervalSet<u16>,
 }
 
 impl PartRange {
     fn new() -> PartRange {
         PartRange {
-            cool: Some(1..4001),
-            musical: Some(1..4001),
-            aero: Some(1..4001),
-            shiny: Some(1..4001),
+            cool: IntervalSet::from(RATINGS),
+            musical: IntervalSet::from(RATINGS),
+            aero: IntervalSet::from(RATINGS),
+            shiny: IntervalSet::from(RATINGS),
         }
     }
+
+    /// Parse a box like `x=100..200,s=1..2000`, missing properties span all ratings.
+    fn parse_query(query: &str) -> PartRange {
+        query
+            .split(',')
+            .filter(|bound| !bound.is_empty())
+            .fold(PartRange::new(), |part, bound| {
+                let (prop, range) = bound
+                    .split_once('=')
+                    .expect("expected `<property>=<range>`");
+                let prop = match prop {
+                    "x" => Property::Cool,
+                    "m" => Property::Musical,
+                    "a" => Property::Aero,
+                    "s" => Property::Shiny,
+                    _ => panic!("unknown property `{}`", prop),
+                };
+                let (start, end) = range.split_once("..").expect("expected `<start>..<end>`");
+                let range = start.parse().unwrap()..end.parse().unwrap();
+                prop.set(&part, IntervalSet::from(range))
+            })
+    }
+
+    fn intersection(&self, other: &PartRange) -> PartRange {
+        PartRange {
+            cool: self.cool.intersection(&other.cool),
+            musical: self.musical.intersection(&other.musical),
+            aero: self.aero.intersection(&other.aero),
+            shiny: self.shiny.intersection(&other.shiny),
+        }
+    }
+
+    /// Number of distinct parts in this range.
+    fn combinations(&self) -> u64 {
+        self.cool.len() * self.musical.len() * self.aero.len() * self.shiny.len()
+    }
+
+    fn is_empty(&self) -> bool {
+        [&self.cool, &self.musical, &self.aero, &self.shiny]
+            .iter()
+            .any(|range| range.is_empty())
+    }
+
+    /// Split into passing and not passing part ranges `[passing, non_passing]`
+    fn split(&self, prop: Property, op: CheckOp) -> [PartRange; 2] {
+        let range = prop.get(self);
+        let passing_op = op.passing();
+
+        let passing = prop.set(self, range.intersection(&passing_op));
+        let non_passing = prop.set(self, range.difference(&passing_op));
+
+        [passing, non_passing]
+    }
 }
 
-#[derive(Debug, Clone, Copy)]
+impl fmt::Display for PartRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let props = [
+            ('x', &self.cool),
+            ('m', &self.musical),
+            ('a', &self.aero),
+            ('s', &self.shiny),
+        ];
+        for (idx, (name, set)) in props.into_iter().enumerate() {
+            if idx > 0 {
+                write!(f, " ")?;
+            }
+            write!(f, "{}=", name)?;
+            if set.is_empty() {
+                write!(f, "{{}}")?;
+            }
+            for (idx, range) in set.ranges().iter().enumerate() {
+                if idx > 0 {
+                    write!(f, "|")?;
+                }
+                write!(f, "{}..{}", range.start, range.end)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 enum Property {
     Cool,
     Musical,
@@ -31,15 +113,15 @@ enum Property {
 }
 
 impl Property {
-    fn get(self, part: &PartRange) -> Option<Range<u16>> {
+    fn get(self, part: &PartRange) -> &IntervalSet<u16> {
         match self {
-            Property::Cool => part.cool.clone(),
-            Property::Musical => part.musical.clone(),
-            Property::Aero => part.aero.clone(),
-            Property::Shiny => part.shiny.clone(),
+            Property::Cool => &part.cool,
+            Property::Musical => &part.musical,
+            Property::Aero => &part.aero,
+            Property::Shiny => &part.shiny,
         }
     }
-    fn set(self, part: &PartRange, range: Option<Range<u16>>) -> PartRange {
+    fn set(self, part: &PartRange, range: IntervalSet<u16>) -> PartRange {
         let mut part = part.clone();
         match self {
             Property::Cool => part.cool = range,
@@ -51,7 +133,7 @@ impl Property {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 enum CheckOp {
     /// Passes every value that is strictly less than
     LessThan(u16),
@@ -60,25 +142,22 @@ enum CheckOp {
 }
 
 impl CheckOp {
-    /// Return the part of the range that passes this check
-    fn passing_subrange(self, range: &Range<u16>) -> Option<Range<u16>> {
+    /// All values that pass this check
+    fn passing(self) -> IntervalSet<u16> {
         match self {
-            CheckOp::LessThan(n) if range.start >= n => None,
-            CheckOp::LessThan(n) => Some(range.start..n),
-            CheckOp::GreaterThan(n) if range.end <= n + 1 => None,
-            CheckOp::GreaterThan(n) => Some((n + 1)..range.end),
+            CheckOp::LessThan(n) => IntervalSet::from(0..n),
+            CheckOp::GreaterThan(n) => IntervalSet::from((n + 1)..u16::MAX),
         }
     }
+}
 
-    /// Return the part of the range that doesn't pass this check
-    fn non_passing_subrange(self, range: &Range<u16>) -> Option<Range<u16>> {
-        match self {
-            // does not pass anything greater or equal to n
-            CheckOp::LessThan(n) => CheckOp::GreaterThan(n - 1).passing_subrange(range),
-            // does not pass anything smaller or equal to n
-            CheckOp::GreaterThan(n) => CheckOp::LessThan(n + 1).passing_subrange(range),
-        }
-    }
+/// Where a part goes next.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Target {
+    Accept,
+    Reject,
+    /// Index of a workflow in `Workflows`
+    Workflow(usize),
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -88,168 +167,453 @@ struct Check {
     /// Which kind of check this is
     op: CheckOp,
     /// Next workflow, if the check passes
-    dst: &'static str,
+    dst: Target,
 }
 
-impl Check {
-    /// Split into passing and not passing part ranges `((passing, dst), non_passing)`
-    fn split_range(self, part: &PartRange) -> [PartRange; 2] {
-        let passing_range = self
-            .prop
-            .get(part)
-            .and_then(|range| self.op.passing_subrange(&range));
-        let passing = self.prop.set(part, passing_range);
-
-        let non_passing_range = self
-            .prop
-            .get(part)
-            .and_then(|range| self.op.non_passing_subrange(&range));
-        let non_passing = self.prop.set(part, non_passing_range);
+#[derive(Debug, Clone)]
+struct Workflow {
+    checks: Vec<Check>,
+    no_match: Target,
+}
 
-        [passing, non_passing]
-    }
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ParseError {
+    /// A workflow with this name was defined twice
+    Duplicate(&'static str),
+    /// `workflow` sends parts to `dst`, which is never defined
+    Undefined {
+        workflow: &'static str,
+        dst: &'static str,
+    },
+    /// There is no `in` workflow to start with
+    MissingStart,
+    /// Parts can be sent around in circles through this workflow
+    Cycle(&'static str),
 }
 
+/// All workflows, referring to each other by index.
 #[derive(Debug, Clone)]
-struct Workflow {
-    checks: Vec<Check>,
-    no_match: &'static str,
+struct Workflows {
+    names: Vec<&'static str>,
+    inner: Vec<Workflow>,
+    /// Index of the `in` workflow
+    start: usize,
 }
 
-impl Workflow {
-    fn process_range(&self, mut range: PartRange, queue: &mut Vec<(&'static str, PartRange)>) {
-        for check in &self.checks {
-            let [passing, non_passing] = check.split_range(&range);
-            queue.push((check.dst, passing));
-            range = non_passing;
+impl Workflows {
+    fn get(&self, id: usize) -> &Workflow {
+        &self.inner[id]
+    }
+    fn name(&self, id: usize) -> &'static str {
+        self.names[id]
+    }
+
+    /// Parse workflows up to the empty line separating them from the parts.
+    fn parse(lines: impl Iterator<Item = &'static str>) -> Result<Workflows, ParseError> {
+        // split `px{a<2006:qkq,m>2090:A,rfg}` into name and rules
+        let definitions = lines
+            .take_while(|line| !line.is_empty())
+            .map(|line| {
+                let (name, rules) = line.split_once('{').unwrap();
+                (name, &rules[..rules.len() - 1])
+            })
+            .collect::<Vec<_>>();
+
+        // intern all names first, so the rules can refer to workflows defined later
+        let names = definitions
+            .iter()
+            .map(|&(name, _)| name)
+            .collect::<Vec<_>>();
+        let mut ids = HashMap::with_capacity(names.len());
+        for (id, &name) in names.iter().enumerate() {
+            if ids.insert(name, id).is_some() {
+                return Err(ParseError::Duplicate(name));
+            }
         }
-        queue.push((self.no_match, range));
+
+        let target = |workflow: &'static str, dst: &'static str| match dst {
+            "A" => Ok(Target::Accept),
+            "R" => Ok(Target::Reject),
+            _ => match ids.get(dst) {
+                Some(&id) => Ok(Target::Workflow(id)),
+                None => Err(ParseError::Undefined { workflow, dst }),
+            },
+        };
+
+        let inner = definitions
+            .iter()
+            .map(|&(name, rules)| {
+                let mut checks = rules.split(',').collect::<Vec<_>>();
+                let no_match = target(name, checks.pop().unwrap())?;
+
+                let checks = checks
+                    .into_iter()
+                    .map(|check| {
+                        // parse `a<2006:qkq`
+                        let (check, dst) = check.split_once(':').unwrap();
+
+                        let prop = match check.as_bytes()[0] {
+                            b'x' => Property::Cool,
+                            b'm' => Property::Musical,
+                            b'a' => Property::Aero,
+                            b's' => Property::Shiny,
+                            _ => panic!(),
+                        };
+
+                        let value = check[2..].parse::<u16>().unwrap();
+                        let op = match check.as_bytes()[1] {
+                            b'<' => CheckOp::LessThan(value),
+                            b'>' => CheckOp::GreaterThan(value),
+                            _ => panic!(),
+                        };
+
+                        Ok(Check {
+                            dst: target(name, dst)?,
+                            op,
+                            prop,
+                        })
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                Ok(Workflow { checks, no_match })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let start = *ids.get("in").ok_or(ParseError::MissingStart)?;
+        let workflows = Workflows {
+            names,
+            inner,
+            start,
+        };
+        workflows.check_cycles()?;
+        Ok(workflows)
+    }
+
+    /// Make sure no workflow can be reached again from itself.
+    fn check_cycles(&self) -> Result<(), ParseError> {
+        #[derive(Clone, Copy, PartialEq, Eq)]
+        enum State {
+            Unvisited,
+            /// On the current path
+            Visiting,
+            /// All successors checked
+            Done,
+        }
+
+        fn visit(workflows: &Workflows, id: usize, states: &mut [State]) -> Result<(), ParseError> {
+            match states[id] {
+                State::Done => return Ok(()),
+                State::Visiting => return Err(ParseError::Cycle(workflows.name(id))),
+                State::Unvisited => states[id] = State::Visiting,
+            }
+
+            let workflow = workflows.get(id);
+            let targets = workflow.checks.iter().map(|check| check.dst);
+            for target in targets.chain([workflow.no_match]) {
+                if let Target::Workflow(next) = target {
+                    visit(workflows, next, states)?;
+                }
+            }
+
+            states[id] = State::Done;
+            Ok(())
+        }
+
+        let mut states = vec![State::Unvisited; self.inner.len()];
+        (0..self.inner.len()).try_for_each(|id| visit(self, id, &mut states))
     }
 }
 
-#[derive(Debug, Clone)]
-struct Workflows {
-    inner: Vec<(u64, Workflow)>,
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Node {
+    /// Accepted (`true`) or rejected (`false`)
+    Verdict(bool),
+    /// Continue with `pass` if the property passes the check, otherwise with `fail`
+    Check {
+        prop: Property,
+        op: CheckOp,
+        pass: usize,
+        fail: usize,
+    },
 }
 
-fn hash_name(name: &str) -> u64 {
-    let mut hasher = std::hash::DefaultHasher::new();
-    name.hash(&mut hasher);
-    hasher.finish()
+/// Workflows compiled into a decision DAG.
+///
+/// Checks that can't fail or can't pass are left out and equal nodes are shared,
+/// so all workflows that always end with the same verdict become the same leaf.
+#[derive(Debug, Clone)]
+struct DecisionTree {
+    nodes: Vec<Node>,
+    root: usize,
 }
 
-impl Workflows {
-    fn new() -> Workflows {
-        Workflows { inner: Vec::new() }
-    }
-
-    fn push(&mut self, name: &str, workflow: Workflow) {
-        let hash = hash_name(name);
-        let idx = self
-            .inner
-            .binary_search_by_key(&hash, |&(hash, _)| hash)
-            .unwrap_err();
-        self.inner.insert(idx, (hash, workflow));
-    }
-    fn get(&self, name: &str) -> Option<&Workflow> {
-        self.inner
-            .binary_search_by_key(&hash_name(name), |&(hash, _)| hash)
-            .ok()
-            .map(|idx| &self.inner[idx].1)
-    }
-
-    /// Process ranges and return final ranges with partition point `p`.
-    /// All elements in `[..p]` are accepted and all in `[p..]` are rejected.
-    fn process_range(&self, range: PartRange) -> (Vec<PartRange>, usize) {
-        let mut queue = Vec::new();
-        self.get("in").unwrap().process_range(range, &mut queue);
-
-        // find the next part range to process, remove it from the queue and return it
-        fn pop_range(
-            ranges: &mut Vec<(&'static str, PartRange)>,
-        ) -> Option<(&'static str, PartRange)> {
-            let idx_rev = ranges
-                .iter()
-                .rev() // pop from the back so fewer elements have to be moved
-                .position(|&(name, _)| name != "R" && name != "A")?;
-            Some(ranges.remove(ranges.len() - idx_rev - 1))
+impl DecisionTree {
+    fn compile(workflows: &Workflows) -> DecisionTree {
+        struct Compiler<'a> {
+            workflows: &'a Workflows,
+            nodes: Vec<Node>,
+            ids: HashMap<Node, usize>,
+            /// Already compiled `(workflow, check index, bounds)`
+            compiled: HashMap<(usize, usize, PartRange), usize>,
         }
 
-        while let Some((next_name, next_range)) = pop_range(&mut queue) {
-            self.get(next_name)
-                .unwrap()
-                .process_range(next_range, &mut queue);
+        impl Compiler<'_> {
+            fn node(&mut self, node: Node) -> usize {
+                match node {
+                    // both outcomes are the same, the check is irrelevant
+                    Node::Check { pass, fail, .. } if pass == fail => pass,
+                    _ => *self.ids.entry(node).or_insert_with(|| {
+                        self.nodes.push(node);
+                        self.nodes.len() - 1
+                    }),
+                }
+            }
+
+            fn destination(&mut self, dst: Target, bounds: &PartRange) -> usize {
+                match dst {
+                    Target::Accept => self.node(Node::Verdict(true)),
+                    Target::Reject => self.node(Node::Verdict(false)),
+                    Target::Workflow(id) => self.checks(id, 0, bounds),
+                }
+            }
+
+            /// Compile the checks of a workflow starting at `check_idx`,
+            /// only parts within `bounds` can get there.
+            fn checks(&mut self, workflow: usize, check_idx: usize, bounds: &PartRange) -> usize {
+                let key = (workflow, check_idx, bounds.clone());
+                if let Some(&id) = self.compiled.get(&key) {
+                    return id;
+                }
+
+                let rules = self.workflows.get(workflow);
+                let id = match rules.checks.get(check_idx) {
+                    None => self.destination(rules.no_match, bounds),
+                    Some(check) => {
+                        let [passing, non_passing] = bounds.split(check.prop, check.op);
+                        match (passing.is_empty(), non_passing.is_empty()) {
+                            (false, false) => {
+                                let pass = self.destination(check.dst, &passing);
+                                let fail = self.checks(workflow, check_idx + 1, &non_passing);
+                                self.node(Node::Check {
+                                    prop: check.prop,
+                                    op: check.op,
+                                    pass,
+                                    fail,
+                                })
+                            }
+                            (false, true) => self.destination(check.dst, &passing),
+                            _ => self.checks(workflow, check_idx + 1, &non_passing),
+                        }
+                    }
+                };
+
+                self.compiled.insert(key, id);
+                id
+            }
+        }
+
+        let mut compiler = Compiler {
+            workflows,
+            nodes: Vec::new(),
+            ids: HashMap::new(),
+            compiled: HashMap::new(),
+        };
+        let root = compiler.checks(workflows.start, 0, &PartRange::new());
+
+        DecisionTree {
+            nodes: compiler.nodes,
+            root,
+        }
+    }
+
+    /// All accepted parts within `range` as disjoint boxes.
+    fn accepted_boxes(&self, range: &PartRange) -> Vec<PartRange> {
+        fn collect(tree: &DecisionTree, node: usize, range: PartRange, boxes: &mut Vec<PartRange>) {
+            if range.is_empty() {
+                return;
+            }
+            match tree.nodes[node] {
+                Node::Verdict(true) => boxes.push(range),
+                Node::Verdict(false) => (),
+                Node::Check {
+                    prop,
+                    op,
+                    pass,
+                    fail,
+                } => {
+                    let [passing, non_passing] = range.split(prop, op);
+                    collect(tree, pass, passing, boxes);
+                    collect(tree, fail, non_passing, boxes);
+                }
+            }
         }
 
-        // sort and determine partition point
-        queue.sort_unstable_by_key(|&(name, _)| name);
-        let partition_point = queue.partition_point(|&(name, _)| name == "A");
+        // the tree was only simplified for valid ratings
+        let range = range.intersection(&PartRange::new());
 
-        // omit names of workstations as they can be inferred
-        let queue = queue.into_iter().map(|(_, range)| range).collect();
+        let mut boxes = Vec::new();
+        collect(self, self.root, range, &mut boxes);
+        boxes
+    }
 
-        (queue, partition_point)
+    /// Number of parts within `range` that are accepted.
+    fn accepted(&self, range: &PartRange) -> u64 {
+        self.accepted_boxes(range)
+            .iter()
+            .map(PartRange::combinations)
+            .sum()
     }
 }
 
 fn main() {
     let mut challenge = advent_of_code_2023::Challenge::start(19, 2);
 
-    let workflows = {
-        let mut workflows = Workflows::new();
-        for line in challenge.input_lines() {
-            if line.is_empty() {
-                // workflows and parts are separated by an empty line
-                break;
+    let workflows = Workflows::parse(challenge.input_lines()).expect("invalid workflows");
+    challenge.finish_parsing();
+
+    let tree = DecisionTree::compile(&workflows);
+    let solution = tree.accepted(&PartRange::new());
+
+    // explore the rules with e.g. `--query=x=100..200,m=1..2000`
+    if let Some(query) =
+        std::env::args().find_map(|arg| arg.strip_prefix("--query=").map(String::from))
+    {
+        let query = PartRange::parse_query(&query);
+        let boxes = tree.accepted_boxes(&query);
+        let count = boxes.iter().map(PartRange::combinations).sum::<u64>();
+
+        println!(
+            "[i] Query {}: {} accepted parts in {} boxes",
+            query,
+            count,
+            boxes.len()
+        );
+        boxes.iter().for_each(|part| println!("[i]   {}", part));
+    }
+
+    challenge.finish(solution);
+}
+
+#[cfg(test)]
+mod tree_test {
+    use super::*;
+
+    const EXAMPLE: &str = "\
+px{a<2006:qkq,m>2090:A,rfg}
+pv{a>1716:R,A}
+lnx{m>1548:A,A}
+rfg{s<537:gd,x>2440:R,A}
+qs{s>3448:A,lnx}
+qkq{x<1416:A,crn}
+crn{x>2662:A,R}
+in{s<1351:px,qqz}
+qqz{s>2770:qs,m<1801:hdj,R}
+gd{a>3333:R,R}
+hdj{m>838:A,pv}
+";
+
+    #[test]
+    fn example() {
+        let tree = DecisionTree::compile(&Workflows::parse(EXAMPLE.lines()).unwrap());
+        assert_eq!(tree.accepted(&PartRange::new()), 167409079868000);
+
+        // `lnx`, `gd` and `qs` (since `lnx` accepts) always end with the same verdict
+        let checks = tree
+            .nodes
+            .iter()
+            .filter(|node| matches!(node, Node::Check { .. }))
+            .count();
+        assert_eq!(checks, 11);
+    }
+
+    #[test]
+    fn dead_branches() {
+        let tree = DecisionTree::compile(
+            &Workflows::parse(["in{x<10:a,R}", "a{x>20:R,m<5:b,A}", "b{m>10:R,A}"].into_iter())
+                .unwrap(),
+        );
+
+        // `x>20` and `m>10` can never pass, so `a` and `b` always accept
+        assert_eq!(tree.nodes.len(), 3);
+        assert_eq!(
+            tree.nodes[tree.root],
+            Node::Check {
+                prop: Property::Cool,
+                op: CheckOp::LessThan(10),
+                pass: 0,
+                fail: 1,
             }
+        );
+        assert_eq!(tree.nodes[0], Node::Verdict(true));
+    }
 
-            // parse `px{a<2006:qkq,m>2090:A,rfg}`
-            let (name, line) = line.split_once('{').unwrap();
-            let mut checks = line[..line.len() - 1].split(',').collect::<Vec<_>>();
-            let no_match = checks.pop().unwrap();
-
-            let checks = checks
-                .into_iter()
-                .map(|check| {
-                    // parse `a<2006:qkq`
-                    let (check, dst) = check.split_once(':').unwrap();
-
-                    let prop = match check.as_bytes()[0] {
-                        b'x' => Property::Cool,
-                        b'm' => Property::Musical,
-                        b'a' => Property::Aero,
-                        b's' => Property::Shiny,
-                        _ => panic!(),
-                    };
-
-                    let value = check[2..].parse::<u16>().unwrap();
-                    let op = match check.as_bytes()[1] {
-                        b'<' => CheckOp::LessThan(value),
-                        b'>' => CheckOp::GreaterThan(value),
-                        _ => panic!(),
-                    };
-
-                    Check { dst, op, prop }
-                })
-                .collect::<Vec<_>>();
-
-            workflows.push(name, Workflow { checks, no_match });
+    #[test]
+    fn query() {
+        let tree = DecisionTree::compile(&Workflows::parse(EXAMPLE.lines()).unwrap());
+        let total = tree.accepted(&PartRange::new());
+
+        let low = PartRange::parse_query("x=1..2001");
+        let high = PartRange::parse_query("x=2001..4001");
+        assert_eq!(tree.accepted(&low) + tree.accepted(&high), total);
+
+        let query = PartRange::parse_query("x=100..200,s=1..3000");
+        let boxes = tree.accepted_boxes(&query);
+        assert!(boxes.iter().all(|part| part.intersection(&query) == *part));
+        for (idx, lhs) in boxes.iter().enumerate() {
+            for rhs in &boxes[idx + 1..] {
+                assert!(lhs.intersection(rhs).is_empty());
+            }
         }
-        workflows
-    };
-    challenge.finish_parsing();
 
-    let (ranges, partition_point) = workflows.process_range(PartRange::new());
+        // parts are only rated `1..4001`
+        let outside = PartRange::parse_query("x=0..10000");
+        assert_eq!(tree.accepted(&outside), total);
+    }
+
+    #[test]
+    fn display() {
+        let part = PartRange::parse_query("m=5..10");
+        assert_eq!(part.to_string(), "x=1..4001 m=5..10 a=1..4001 s=1..4001");
+    }
+}
 
-    let solution = ranges[..partition_point]
-        .iter()
-        .map(|range| {
-            range.cool.as_ref().map(|r| r.len()).unwrap_or(0)
-                * range.musical.as_ref().map(|r| r.len()).unwrap_or(0)
-                * range.aero.as_ref().map(|r| r.len()).unwrap_or(0)
-                * range.shiny.as_ref().map(|r| r.len()).unwrap_or(0)
-        })
-        .sum::<usize>();
+#[cfg(test)]
+mod parse_test {
+    use super::*;
 
-    challenge.finish(solution);
+    fn parse(lines: &[&'static str]) -> Result<Workflows, ParseError> {
+        Workflows::parse(lines.iter().copied())
+    }
+
+    #[test]
+    fn interned() {
+        let workflows = parse(&["b{x<10:A,R}", "in{m>5:b,a}", "a{b}"]).unwrap();
+        assert_eq!(workflows.name(workflows.start), "in");
+        assert_eq!(
+            workflows.get(workflows.start).checks[0].dst,
+            Target::Workflow(0)
+        );
+        assert_eq!(workflows.get(workflows.start).no_match, Target::Workflow(2));
+        assert_eq!(workflows.name(2), "a");
+    }
+
+    #[test]
+    fn invalid() {
+        assert_eq!(
+            parse(&["in{x<10:px,A}"]).unwrap_err(),
+            ParseError::Undefined {
+                workflow: "in",
+                dst: "px",
+            }
+        );
+        assert_eq!(
+            parse(&["in{A}", "in{R}"]).unwrap_err(),
+            ParseError::Duplicate("in")
+        );
+        assert_eq!(parse(&["px{A}"]).unwrap_err(), ParseError::MissingStart);
+        assert!(matches!(
+            parse(&["in{x<10:a,A}", "a{m>5:b,R}", "b{a}"]).unwrap_err(),
+            ParseError::Cycle("a" | "b")
+        ));
+    }
 }
@@ -1,10 +1,8 @@
-use std::fmt::Write;
+use std::{collections::VecDeque, fmt::Write};
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
-struct Pos {
-    idx: usize,
-    count: usize,
-}
+use advent_of_code_2023::animation::{Animation, Color, Overlay};
+
+const STEPS: usize = 26_501_365;
 
 #[derive(Debug, Clone, Copy)]
 enum Dir {
@@ -15,45 +13,13 @@ enum Dir {
 }
 
 impl Dir {
-    /// Move the point with wrap-around
-    fn move_pos(self, pos: Pos, map: &Map) -> Pos {
-        let Pos { idx, count } = pos;
+    fn move_point(self, point: usize, map: &Map) -> Option<usize> {
         match self {
-            Dir::Up if idx >= map.width => Pos {
-                idx: idx - map.width,
-                count,
-            },
-            Dir::Up => Pos {
-                idx: map.data.len() - map.width + idx,
-                count,
-            },
-
-            Dir::Down if idx < map.data.len() - map.width => Pos {
-                idx: idx + map.width,
-                count,
-            },
-            Dir::Down => Pos {
-                idx: idx - map.width * (map.height() - 1),
-                count,
-            },
-
-            Dir::Left if idx % map.width != 0 => Pos {
-                idx: idx - 1,
-                count,
-            },
-            Dir::Left => Pos {
-                idx: idx + (map.width - 1),
-                count,
-            },
-
-            Dir::Right if idx % map.width != map.width - 1 => Pos {
-                idx: idx + 1,
-                count,
-            },
-            Dir::Right => Pos {
-                idx: idx - (map.width - 1),
-                count,
-            },
+            Dir::Up if point >= map.width => Some(point - map.width),
+            Dir::Down if point < map.data.len() - map.width => Some(point + map.width),
+            Dir::Left if !point.is_multiple_of(map.width) => Some(point - 1),
+            Dir::Right if point % map.width != map.width - 1 => Some(point + 1),
+            _ => None,
         }
     }
 }
@@ -72,21 +38,46 @@ impl Map {
         self.data.len() / self.width
     }
 
-    fn step(&self, curr_pos: Pos, new_pos_buf: &mut Vec<Pos>) {
-        let reachable = [Dir::Up, Dir::Down, Dir::Left, Dir::Right]
-            .into_iter()
-            .map(|dir| {
-                let new_pos = dir.move_pos(curr_pos, self);
-                (self.data[new_pos.idx] != b'#').then_some(new_pos)
-            })
-            .filter_map(|new_pos| new_pos);
-
-        reachable.for_each(|new_pos| {
-            match new_pos_buf.binary_search_by_key(&new_pos.idx, |p| p.idx) {
-                Ok(idx) => new_pos_buf[idx].count += curr_pos.count,
-                Err(idx) => new_pos_buf.insert(idx, new_pos),
+    /// Repeat the map `n` times in both directions.
+    ///
+    /// The start tile is only kept in the center copy, all other copies get a plot instead.
+    fn tiled(&self, n: usize) -> Map {
+        let width = self.width * n;
+        let mut data = Vec::with_capacity(self.data.len() * n * n);
+        for tile_row in 0..n {
+            for row_idx in 0..self.height() {
+                for tile_col in 0..n {
+                    let is_center = tile_row == n / 2 && tile_col == n / 2;
+                    data.extend(self.row(row_idx).iter().map(|&b| match b {
+                        b'S' if !is_center => b'.',
+                        b => b,
+                    }));
+                }
             }
-        });
+        }
+        Map { data, width }
+    }
+
+    /// Breadth-first search from `start`, unreachable plots have a distance of `u32::MAX`.
+    fn distances(&self, start: usize) -> Vec<u32> {
+        let mut dists = vec![u32::MAX; self.data.len()];
+        let mut queue = VecDeque::from([start]);
+        dists[start] = 0;
+
+        while let Some(pos) = queue.pop_front() {
+            let next_dist = dists[pos] + 1;
+            [Dir::Up, Dir::Down, Dir::Left, Dir::Right]
+                .into_iter()
+                .filter_map(|dir| dir.move_point(pos, self))
+                .for_each(|new_pos| {
+                    if self.data[new_pos] != b'#' && dists[new_pos] == u32::MAX {
+                        dists[new_pos] = next_dist;
+                        queue.push_back(new_pos);
+                    }
+                });
+        }
+
+        dists
     }
 }
 
@@ -104,8 +95,39 @@ impl std::fmt::Debug for Map {
     }
 }
 
+/// Number of plots that can be the final position after exactly `steps` steps.
+///
+/// A plot at distance `d <= steps` is reachable if `d` has the same parity as `steps`,
+/// because the remaining steps can be spent walking back and forth.
+fn reachable_in(dists: &[u32], steps: usize) -> usize {
+    dists
+        .iter()
+        .filter(|&&d| d != u32::MAX && d as usize <= steps && d as usize % 2 == steps % 2)
+        .count()
+}
+
+/// Show all plots reachable after `0..=max_steps` steps, one frame each.
+fn animate(animation: &mut Animation, map: &Map, dists: &[u32], max_steps: usize) {
+    for i in 0..=max_steps {
+        let reached = dists
+            .iter()
+            .enumerate()
+            .filter(|&(_, &d)| d != u32::MAX && d as usize <= i && d as usize % 2 == i % 2)
+            .map(|(idx, _)| idx)
+            .collect::<Vec<_>>();
+
+        animation.frame(
+            format_args!("step {:>3}, positions: {}", i, reached.len()),
+            &map.data,
+            map.width,
+            &[Overlay::new(&reached, Some('O'), Color::Green)],
+        );
+    }
+}
+
 fn main() {
     let mut challenge = advent_of_code_2023::Challenge::start(21, 2);
+    let mut animation = Animation::from_args();
 
     let map = {
         let width = challenge.input_lines().next().unwrap().len();
@@ -118,37 +140,83 @@ fn main() {
 
     challenge.finish_parsing();
 
+    // the extrapolation relies on a square map with the start in its center,
+    // which has a free row and column leading straight to the edges
+    let size = map.width;
     let start_pos = map.data.iter().position(|&b| b == b'S').unwrap();
+    assert_eq!(map.height(), size, "map is not square");
+    assert_eq!(
+        start_pos,
+        (size / 2) * size + size / 2,
+        "start is not centered"
+    );
+
+    // walking `size` steps further reaches exactly one more ring of map copies,
+    // so the number of reachable plots grows quadratically in the number of rings
+    let rem = STEPS % size;
+    let rings = STEPS / size;
+
+    // 5x5 copies are enough to sample the first three rings without hitting the border
+    let tiled = map.tiled(5);
+    let tiled_start = tiled.data.iter().position(|&b| b == b'S').unwrap();
+    let dists = tiled.distances(tiled_start);
+
+    if animation.is_enabled() {
+        animate(&mut animation, &tiled, &dists, rem + 2 * size);
+    }
 
-    let mut positions = vec![Pos {
-        idx: start_pos,
-        count: 1,
-    }];
-    let mut new_positions = vec![];
+    let [y0, y1, y2] = [0, 1, 2].map(|ring| reachable_in(&dists, rem + ring * size) as u64);
 
-    for i in 0..50 {
-        let prev_len = positions.len();
-        for pos in positions.drain(..) {
-            map.step(pos, &mut new_positions);
-        }
-        std::mem::swap(&mut positions, &mut new_positions);
-
-        println!("\x1B[2J\x1B[1;1H");
-        println!(
-            "[i] {:>3}: positions: {} ({:>4})",
-            i,
-            positions.len(),
-            positions.len() as isize - prev_len as isize
-        );
+    // Newton's forward differences for f(0) = y0, f(1) = y1, f(2) = y2
+    let n = rings as u64;
+    let solution = y0 + n * (y1 - y0) + n * (n - 1) / 2 * (y2 + y0 - 2 * y1);
 
-        let mut dbg = map.clone();
-        positions.iter().for_each(|p| dbg.data[p.idx] = b'O');
-        println!("[i] Map({}):\n{:?}\n", i, dbg);
+    challenge.finish(solution);
+}
 
-        std::thread::sleep(std::time::Duration::from_millis(250));
+#[cfg(test)]
+mod garden_test {
+    use super::Map;
+
+    const EXAMPLE: &str = "\
+...........
+.....###.#.
+.###.##..#.
+..#.#...#..
+....#.#....
+.##..S####.
+.##..#...#.
+.......##..
+.##.#.####.
+.##..##.##.
+...........";
+
+    fn example_map() -> Map {
+        Map {
+            data: EXAMPLE.lines().flat_map(str::bytes).collect(),
+            width: EXAMPLE.lines().next().unwrap().len(),
+        }
     }
 
-    let solution = positions.len();
+    /// Plots reachable after `steps` steps on `n` by `n` copies of the example.
+    fn reachable(n: usize, steps: usize) -> usize {
+        let map = example_map().tiled(n);
+        let start = map.data.iter().position(|&b| b == b'S').unwrap();
+        super::reachable_in(&map.distances(start), steps)
+    }
 
-    challenge.finish(solution);
+    #[test]
+    fn example() {
+        assert_eq!(reachable(1, 6), 16);
+        assert_eq!(reachable(11, 50), 1594);
+        assert_eq!(reachable(21, 100), 6536);
+    }
+
+    #[test]
+    fn single_start() {
+        // only the center copy keeps its start
+        let map = example_map().tiled(3);
+        assert_eq!(map.data.iter().filter(|&&b| b == b'S').count(), 1);
+        assert_eq!(map.data.iter().position(|&b| b == b'S'), Some(16 * 33 + 16));
+    }
 }
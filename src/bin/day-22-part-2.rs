@@ -0,0 +1,182 @@
+use std::collections::VecDeque;
+
+use smallvec::SmallVec;
+
+#[derive(Debug, Clone, Copy)]
+struct Point {
+    x: usize,
+    y: usize,
+    z: usize,
+}
+
+impl Point {
+    fn parse(text: &str) -> Point {
+        let mut coords = text.split(',').map(|num| num.parse::<usize>().unwrap());
+        let mut next = || coords.next().unwrap();
+        Point {
+            x: next(),
+            y: next(),
+            z: next(),
+        }
+    }
+}
+
+/// A brick spanning all cells between `from` and `to` (inclusive).
+///
+/// Each coordinate of `from` is at most the one of `to`.
+#[derive(Debug, Clone, Copy)]
+struct Brick {
+    from: Point,
+    to: Point,
+}
+
+impl Brick {
+    fn parse(line: &str) -> Brick {
+        let (from, to) = line.split_once('~').unwrap();
+        let (from, to) = (Point::parse(from), Point::parse(to));
+        Brick {
+            from: Point {
+                x: from.x.min(to.x),
+                y: from.y.min(to.y),
+                z: from.z.min(to.z),
+            },
+            to: Point {
+                x: from.x.max(to.x),
+                y: from.y.max(to.y),
+                z: from.z.max(to.z),
+            },
+        }
+    }
+
+    /// Indices into a height map of the given width covered by the brick seen from above.
+    fn footprint(&self, width: usize) -> impl Iterator<Item = usize> + '_ {
+        (self.from.y..=self.to.y)
+            .flat_map(move |y| (self.from.x..=self.to.x).map(move |x| y * width + x))
+    }
+}
+
+/// Which bricks rest on top of each other after all of them came to rest.
+struct Stack {
+    /// Bricks directly on top of the brick.
+    supports: Vec<SmallVec<[usize; 4]>>,
+    /// Bricks directly below the brick.
+    supported_by: Vec<SmallVec<[usize; 4]>>,
+}
+
+/// Let all bricks fall as far as possible, lowest first.
+fn settle(bricks: &mut [Brick]) -> Stack {
+    bricks.sort_unstable_by_key(|brick| brick.from.z);
+
+    let width = bricks.iter().map(|brick| brick.to.x).max().unwrap_or(0) + 1;
+    let depth = bricks.iter().map(|brick| brick.to.y).max().unwrap_or(0) + 1;
+
+    // top z-coordinate and topmost brick of every column
+    let mut heights: Vec<(usize, Option<usize>)> = vec![(0, None); width * depth];
+
+    let mut supports = vec![SmallVec::new(); bricks.len()];
+    let mut supported_by = vec![SmallVec::<[usize; 4]>::new(); bricks.len()];
+
+    for (brick_idx, brick) in bricks.iter_mut().enumerate() {
+        let floor = brick
+            .footprint(width)
+            .map(|idx| heights[idx].0)
+            .max()
+            .unwrap();
+
+        // every brick whose top is directly below the new position supports this one
+        brick.footprint(width).for_each(|idx| match heights[idx] {
+            (z, Some(below)) if z == floor && !supported_by[brick_idx].contains(&below) => {
+                supported_by[brick_idx].push(below);
+                supports[below].push(brick_idx);
+            }
+            _ => (),
+        });
+
+        let fall = brick.from.z - (floor + 1);
+        brick.from.z -= fall;
+        brick.to.z -= fall;
+
+        let top = brick.to.z;
+        brick
+            .footprint(width)
+            .for_each(|idx| heights[idx] = (top, Some(brick_idx)));
+    }
+
+    Stack {
+        supports,
+        supported_by,
+    }
+}
+
+/// Number of other bricks that fall if `removed` is disintegrated.
+fn chain_reaction(stack: &Stack, removed: usize, supports_left: &mut [usize]) -> usize {
+    // how many of its supporters are still in place, a brick falls once this hits zero
+    supports_left
+        .iter_mut()
+        .zip(&stack.supported_by)
+        .for_each(|(left, below)| *left = below.len());
+
+    let mut falling = 0;
+    let mut queue = VecDeque::from([removed]);
+
+    while let Some(brick_idx) = queue.pop_front() {
+        stack.supports[brick_idx].iter().for_each(|&above| {
+            supports_left[above] -= 1;
+            if supports_left[above] == 0 {
+                falling += 1;
+                queue.push_back(above);
+            }
+        });
+    }
+
+    falling
+}
+
+fn main() {
+    let mut challenge = advent_of_code_2023::Challenge::start(22, 2);
+
+    let mut bricks = challenge
+        .input_lines()
+        .map(Brick::parse)
+        .collect::<Vec<_>>();
+
+    challenge.finish_parsing();
+
+    let stack = settle(&mut bricks);
+
+    let mut supports_left = vec![0; bricks.len()];
+    let solution = (0..bricks.len())
+        .map(|brick_idx| chain_reaction(&stack, brick_idx, &mut supports_left))
+        .sum::<usize>();
+
+    challenge.finish(solution);
+}
+
+#[cfg(test)]
+mod settle_test {
+    use super::{chain_reaction, settle, Brick};
+
+    const EXAMPLE: [&str; 7] = [
+        "1,0,1~1,2,1",
+        "0,0,2~2,0,2",
+        "0,2,3~2,2,3",
+        "0,0,4~0,2,4",
+        "2,0,5~2,2,5",
+        "0,1,6~2,1,6",
+        "1,1,8~1,1,9",
+    ];
+
+    #[test]
+    fn example() {
+        let mut bricks = EXAMPLE.map(Brick::parse);
+        let stack = settle(&mut bricks);
+
+        let mut supports_left = vec![0; bricks.len()];
+        let falling = (0..bricks.len())
+            .map(|brick_idx| chain_reaction(&stack, brick_idx, &mut supports_left))
+            .collect::<Vec<_>>();
+        // only A and F make other bricks fall
+        assert_eq!(falling, [6, 0, 0, 0, 0, 1, 0]);
+        assert_eq!(falling.iter().sum::<usize>(), 7);
+    }
+}
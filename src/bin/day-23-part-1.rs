@@ -0,0 +1,198 @@
+use smallvec::SmallVec;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Dir {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl Dir {
+    fn move_point(self, point: usize, map: &Map) -> Option<usize> {
+        match self {
+            Dir::Up if point >= map.width => Some(point - map.width),
+            Dir::Down if point < map.data.len() - map.width => Some(point + map.width),
+            Dir::Left if !point.is_multiple_of(map.width) => Some(point - 1),
+            Dir::Right if point % map.width != map.width - 1 => Some(point + 1),
+            _ => None,
+        }
+    }
+
+    /// The direction a slope tile forces us to walk in.
+    fn of_slope(tile: u8) -> Option<Dir> {
+        match tile {
+            b'^' => Some(Dir::Up),
+            b'v' => Some(Dir::Down),
+            b'<' => Some(Dir::Left),
+            b'>' => Some(Dir::Right),
+            _ => None,
+        }
+    }
+}
+
+struct Map {
+    data: Vec<u8>,
+    width: usize,
+    /// Whether slopes can only be walked downhill.
+    slippery: bool,
+}
+
+impl Map {
+    /// Tiles reachable in one step from `pos`.
+    fn neighbors(&self, pos: usize) -> impl Iterator<Item = usize> + '_ {
+        let forced = self
+            .slippery
+            .then(|| Dir::of_slope(self.data[pos]))
+            .flatten();
+
+        [Dir::Up, Dir::Down, Dir::Left, Dir::Right]
+            .into_iter()
+            .filter(move |&dir| forced.is_none_or(|forced| forced == dir))
+            .filter_map(move |dir| dir.move_point(pos, self))
+            .filter(|&next| self.data[next] != b'#')
+    }
+
+    fn is_junction(&self, pos: usize) -> bool {
+        self.data[pos] != b'#'
+            && [Dir::Up, Dir::Down, Dir::Left, Dir::Right]
+                .into_iter()
+                .filter_map(|dir| dir.move_point(pos, self))
+                .filter(|&next| self.data[next] != b'#')
+                .count()
+                > 2
+    }
+}
+
+/// The map compressed into the junctions and the corridors between them.
+struct Graph {
+    /// `(junction, corridor length)` for every junction reachable from the junction.
+    edges: Vec<SmallVec<[(usize, u32); 4]>>,
+    start: usize,
+    end: usize,
+}
+
+impl Graph {
+    fn new(map: &Map) -> Graph {
+        let start_pos = map.data.iter().position(|&b| b == b'.').unwrap();
+        let end_pos = map.data.iter().rposition(|&b| b == b'.').unwrap();
+
+        let mut junctions = vec![start_pos];
+        junctions.extend((0..map.data.len()).filter(|&pos| map.is_junction(pos)));
+        junctions.push(end_pos);
+        assert!(junctions.len() <= 64, "too many junctions for a bitmask");
+
+        let edges = junctions
+            .iter()
+            .map(|&from| {
+                map.neighbors(from)
+                    .filter_map(|first| {
+                        // follow the corridor until the next junction or a dead end
+                        let (mut prev, mut curr, mut len) = (from, first, 1);
+                        loop {
+                            if let Ok(to) = junctions.binary_search(&curr) {
+                                break Some((to, len));
+                            }
+                            let next = map.neighbors(curr).find(|&next| next != prev)?;
+                            (prev, curr, len) = (curr, next, len + 1);
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+
+        Graph {
+            edges,
+            start: 0,
+            end: junctions.len() - 1,
+        }
+    }
+
+    /// Length of the longest path from start to end that visits no junction twice.
+    fn longest_hike(&self) -> Option<u32> {
+        // once we reach the only junction leading to the end, we have to go there,
+        // every other path would cut us off from the end
+        let (last_junction, last_len) =
+            self.edges.iter().enumerate().find_map(|(from, edges)| {
+                let &(_, len) = edges.iter().find(|&&(to, _)| to == self.end)?;
+                Some((from, len))
+            })?;
+
+        fn dfs(graph: &Graph, target: usize, vertex: usize, visited: u64) -> Option<u32> {
+            if vertex == target {
+                return Some(0);
+            }
+            graph.edges[vertex]
+                .iter()
+                .filter(|&&(to, _)| visited & (1 << to) == 0)
+                .filter_map(|&(to, len)| Some(len + dfs(graph, target, to, visited | (1 << to))?))
+                .max()
+        }
+
+        let hike = dfs(self, last_junction, self.start, 1 << self.start)?;
+        Some(hike + last_len)
+    }
+}
+
+fn main() {
+    let mut challenge = advent_of_code_2023::Challenge::start(23, 1);
+
+    let map = {
+        let width = challenge.input_lines().next().unwrap().len();
+        let mut data = Vec::new();
+        challenge
+            .input_lines()
+            .for_each(|line| data.extend_from_slice(line.as_bytes()));
+        Map {
+            data,
+            width,
+            slippery: true,
+        }
+    };
+
+    challenge.finish_parsing();
+
+    let graph = Graph::new(&map);
+    let solution = graph.longest_hike().unwrap();
+
+    challenge.finish(solution);
+}
+
+#[cfg(test)]
+mod hike_test {
+    use super::{Graph, Map};
+
+    const EXAMPLE: &str = "#.#####################
+#.......#########...###
+#######.#########.#.###
+###.....#.>.>.###.#.###
+###v#####.#v#.###.#.###
+###.>...#.#.#.....#...#
+###v###.#.#.#########.#
+###...#.#.#.......#...#
+#####.#.#.#######.#.###
+#.....#.#.#.......#...#
+#.#####.#.#.#########v#
+#.#...#...#...###...>.#
+#.#.#v#######v###.###v#
+#...#.>.#...>.>.#.###.#
+#####v#.#.###v#.#.###.#
+#.....#...#...#.#.#...#
+#.#########.###.#.#.###
+#...###...#...#...#.###
+###.###.#.###v#####v###
+#...#...#.#.>.>.#.>.###
+#.###.###.#.###.#.#v###
+#.....###...###...#...#
+#####################.#";
+
+    #[test]
+    fn example() {
+        let map = Map {
+            data: EXAMPLE.lines().flat_map(str::bytes).collect(),
+            width: EXAMPLE.lines().next().unwrap().len(),
+            slippery: true,
+        };
+        assert_eq!(Graph::new(&map).longest_hike(), Some(94));
+    }
+}
@@ -0,0 +1,112 @@
+use std::ops::RangeInclusive;
+
+const TEST_AREA: RangeInclusive<i128> = 200_000_000_000_000..=400_000_000_000_000;
+
+#[derive(Debug, Clone, Copy)]
+struct Hail {
+    pos: [i128; 3],
+    vel: [i128; 3],
+}
+
+impl Hail {
+    fn parse(line: &str) -> Hail {
+        fn triple(text: &str) -> [i128; 3] {
+            let mut nums = text
+                .split(',')
+                .map(|num| num.trim().parse::<i128>().unwrap());
+            [(); 3].map(|_| nums.next().unwrap())
+        }
+        let (pos, vel) = line.split_once('@').unwrap();
+        Hail {
+            pos: triple(pos),
+            vel: triple(vel),
+        }
+    }
+}
+
+/// 2D cross product `lhs.x * rhs.y - lhs.y * rhs.x`.
+fn cross(lhs: [i128; 2], rhs: [i128; 2]) -> i128 {
+    lhs[0] * rhs[1] - lhs[1] * rhs[0]
+}
+
+/// Whether the future paths of both hailstones cross inside `area`, ignoring the z-axis.
+///
+/// Everything is kept as integer fractions with a common positive denominator,
+/// so there is no rounding involved. Parallel paths never cross.
+fn paths_cross_inside(lhs: &Hail, rhs: &Hail, area: &RangeInclusive<i128>) -> bool {
+    let (p1, v1) = ([lhs.pos[0], lhs.pos[1]], [lhs.vel[0], lhs.vel[1]]);
+    let (p2, v2) = ([rhs.pos[0], rhs.pos[1]], [rhs.vel[0], rhs.vel[1]]);
+
+    // p1 + t * v1 = p2 + s * v2 with t = t_num / den and s = s_num / den
+    let diff = [p2[0] - p1[0], p2[1] - p1[1]];
+    let (mut den, mut t_num, mut s_num) = (cross(v1, v2), cross(diff, v2), cross(diff, v1));
+    if den == 0 {
+        return false;
+    }
+    if den < 0 {
+        (den, t_num, s_num) = (-den, -t_num, -s_num);
+    }
+
+    // both hailstones have to get there in the future
+    if t_num < 0 || s_num < 0 {
+        return false;
+    }
+
+    // compare the intersection `(p1 * den + t_num * v1) / den` against the area
+    let (lo, hi) = (*area.start() * den, *area.end() * den);
+    (0..2).all(|axis| {
+        let coord = p1[axis] * den + t_num * v1[axis];
+        lo <= coord && coord <= hi
+    })
+}
+
+fn count_crossings(hail: &[Hail], area: &RangeInclusive<i128>) -> usize {
+    hail.iter()
+        .enumerate()
+        .map(|(idx, lhs)| {
+            hail[idx + 1..]
+                .iter()
+                .filter(|rhs| paths_cross_inside(lhs, rhs, area))
+                .count()
+        })
+        .sum()
+}
+
+fn main() {
+    let mut challenge = advent_of_code_2023::Challenge::start(24, 1);
+
+    let hail = challenge.input_lines().map(Hail::parse).collect::<Vec<_>>();
+
+    challenge.finish_parsing();
+
+    let solution = count_crossings(&hail, &TEST_AREA);
+
+    challenge.finish(solution);
+}
+
+#[cfg(test)]
+mod crossing_test {
+    use super::{count_crossings, Hail};
+
+    #[test]
+    fn example() {
+        let hail = [
+            "19, 13, 30 @ -2,  1, -2",
+            "18, 19, 22 @ -1, -1, -2",
+            "20, 25, 34 @ -2, -2, -4",
+            "12, 31, 28 @ -1, -2, -1",
+            "20, 19, 15 @  1, -5, -3",
+        ]
+        .map(Hail::parse);
+        assert_eq!(count_crossings(&hail, &(7..=27)), 2);
+    }
+
+    #[test]
+    fn touching_the_border() {
+        // both cross exactly at (10, 10)
+        let hail = ["0, 10, 0 @ 1, 0, 0", "10, 0, 0 @ 0, 1, 0"].map(Hail::parse);
+        assert_eq!(count_crossings(&hail, &(10..=20)), 1);
+        assert_eq!(count_crossings(&hail, &(0..=9)), 0);
+        assert_eq!(count_crossings(&hail, &(11..=20)), 0);
+    }
+}
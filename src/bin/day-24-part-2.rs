@@ -0,0 +1,183 @@
+#[derive(Debug, Clone, Copy)]
+struct Hail {
+    pos: [i128; 3],
+    vel: [i128; 3],
+}
+
+impl Hail {
+    fn parse(line: &str) -> Hail {
+        fn triple(text: &str) -> [i128; 3] {
+            let mut nums = text
+                .split(',')
+                .map(|num| num.trim().parse::<i128>().unwrap());
+            [(); 3].map(|_| nums.next().unwrap())
+        }
+        let (pos, vel) = line.split_once('@').unwrap();
+        Hail {
+            pos: triple(pos),
+            vel: triple(vel),
+        }
+    }
+
+    /// Position and velocity as seen from `frame`.
+    fn relative_to(&self, frame: &Hail) -> Hail {
+        Hail {
+            pos: [0, 1, 2].map(|axis| self.pos[axis] - frame.pos[axis]),
+            vel: [0, 1, 2].map(|axis| self.vel[axis] - frame.vel[axis]),
+        }
+    }
+
+    /// Whether both hailstones are at the same position at the same (integer) time `t >= 0`.
+    fn collides_with(&self, other: &Hail) -> bool {
+        let rel = other.relative_to(self);
+        // find the time from the first axis that moves, then check all axes at that time
+        let Some(axis) = (0..3).find(|&axis| rel.vel[axis] != 0) else {
+            return rel.pos == [0, 0, 0];
+        };
+        if rel.pos[axis] % rel.vel[axis] != 0 {
+            return false;
+        }
+        let t = -rel.pos[axis] / rel.vel[axis];
+        t >= 0 && (0..3).all(|axis| rel.pos[axis] + t * rel.vel[axis] == 0)
+    }
+}
+
+/// Arithmetic modulo the Mersenne prime `2^61 - 1`.
+///
+/// The linear system below has coefficients around `10^14` and its determinants don't fit into
+/// `i128`. Solving it modulo a prime is exact and, since the solution is an integer well below
+/// `2^60` in magnitude, it can be read back directly from its symmetric residue.
+mod modp {
+    pub const P: u64 = (1 << 61) - 1;
+
+    pub fn from_i128(value: i128) -> u64 {
+        value.rem_euclid(P as i128) as u64
+    }
+    pub fn to_i128(value: u64) -> i128 {
+        if value > P / 2 {
+            value as i128 - P as i128
+        } else {
+            value as i128
+        }
+    }
+    pub fn mul(lhs: u64, rhs: u64) -> u64 {
+        (lhs as u128 * rhs as u128 % P as u128) as u64
+    }
+    pub fn sub(lhs: u64, rhs: u64) -> u64 {
+        (lhs + P - rhs) % P
+    }
+    pub fn inv(value: u64) -> u64 {
+        // Fermat's little theorem
+        let (mut base, mut exp, mut acc) = (value, P - 2, 1);
+        while exp > 0 {
+            if exp & 1 == 1 {
+                acc = mul(acc, base);
+            }
+            base = mul(base, base);
+            exp >>= 1;
+        }
+        acc
+    }
+
+    /// Solve `A * x = b` given as rows `[A | b]` by Gauss-Jordan elimination.
+    ///
+    /// Returns `None` if the system is singular.
+    pub fn solve<const N: usize, const M: usize>(mut rows: [[u64; M]; N]) -> Option<[u64; N]> {
+        assert_eq!(M, N + 1);
+        for col in 0..N {
+            let pivot = (col..N).find(|&row| rows[row][col] != 0)?;
+            rows.swap(col, pivot);
+
+            let inv = inv(rows[col][col]);
+            rows[col].iter_mut().for_each(|v| *v = mul(*v, inv));
+
+            let pivot_row = rows[col];
+            for row in (0..N).filter(|&row| row != col) {
+                let factor = rows[row][col];
+                rows[row]
+                    .iter_mut()
+                    .zip(&pivot_row)
+                    .skip(col)
+                    .for_each(|(v, &p)| *v = sub(*v, mul(factor, p)));
+            }
+        }
+        Some(rows.map(|row| row[N]))
+    }
+}
+
+/// Find position and velocity of the rock on the two given axes.
+///
+/// In the frame of `hail[0]`, that hailstone rests at the origin, so the rock's path goes through
+/// the origin and its position `P` is parallel to its velocity `V`. For every other hailstone
+/// `(q, w)` the paths have to cross, `(P - q) x (V - w) = 0`, which drops the quadratic term
+/// `P x V` and leaves the linear equation
+///
+/// `P_a * w_b - P_b * w_a + q_a * V_b - q_b * V_a = q_a * w_b - q_b * w_a`
+///
+/// Returns `[P_a, P_b, V_a, V_b]` in the original frame.
+fn solve_plane(hail: &[Hail], a: usize, b: usize) -> Option<[i128; 4]> {
+    let frame = hail[0];
+    let rows = hail[1..].windows(4).find_map(|window| {
+        let rows = [0, 1, 2, 3].map(|idx| {
+            let Hail { pos: q, vel: w } = window[idx].relative_to(&frame);
+            [w[b], -w[a], -q[b], q[a], q[a] * w[b] - q[b] * w[a]].map(modp::from_i128)
+        });
+        modp::solve(rows)
+    })?;
+
+    let [pa, pb, va, vb] = rows.map(modp::to_i128);
+    Some([
+        pa + frame.pos[a],
+        pb + frame.pos[b],
+        va + frame.vel[a],
+        vb + frame.vel[b],
+    ])
+}
+
+/// The rock that hits every hailstone, if there is exactly one.
+fn throw_rock(hail: &[Hail]) -> Option<Hail> {
+    let [px, py, vx, vy] = solve_plane(hail, 0, 1)?;
+    let [_, pz, _, vz] = solve_plane(hail, 0, 2)?;
+    let rock = Hail {
+        pos: [px, py, pz],
+        vel: [vx, vy, vz],
+    };
+
+    // the solution was only derived from a few hailstones and modulo a prime
+    hail.iter()
+        .all(|hail| rock.collides_with(hail))
+        .then_some(rock)
+}
+
+fn main() {
+    let mut challenge = advent_of_code_2023::Challenge::start(24, 2);
+
+    let hail = challenge.input_lines().map(Hail::parse).collect::<Vec<_>>();
+
+    challenge.finish_parsing();
+
+    let rock = throw_rock(&hail).expect("no rock hits every hailstone");
+    let solution = rock.pos.iter().sum::<i128>();
+
+    challenge.finish(solution);
+}
+
+#[cfg(test)]
+mod rock_test {
+    use super::{throw_rock, Hail};
+
+    #[test]
+    fn example() {
+        let hail = [
+            "19, 13, 30 @ -2,  1, -2",
+            "18, 19, 22 @ -1, -1, -2",
+            "20, 25, 34 @ -2, -2, -4",
+            "12, 31, 28 @ -1, -2, -1",
+            "20, 19, 15 @  1, -5, -3",
+        ]
+        .map(Hail::parse);
+        let rock = throw_rock(&hail).unwrap();
+        assert_eq!(rock.pos, [24, 13, 10]);
+        assert_eq!(rock.vel, [-3, 1, 2]);
+    }
+}
@@ -0,0 +1,165 @@
+use std::collections::{HashMap, VecDeque};
+
+/// Undirected graph where every edge has a capacity of one in both directions.
+struct Graph {
+    names: Vec<&'static str>,
+    /// `(neighbor, edge index)` for every vertex
+    adjacent: Vec<Vec<(usize, usize)>>,
+    edges: Vec<(usize, usize)>,
+}
+
+impl Graph {
+    fn parse(lines: impl Iterator<Item = &'static str>) -> Graph {
+        let mut ids = HashMap::new();
+        let mut graph = Graph {
+            names: Vec::new(),
+            adjacent: Vec::new(),
+            edges: Vec::new(),
+        };
+
+        let mut intern = |graph: &mut Graph, name: &'static str| {
+            *ids.entry(name).or_insert_with(|| {
+                graph.names.push(name);
+                graph.adjacent.push(Vec::new());
+                graph.names.len() - 1
+            })
+        };
+
+        lines.for_each(|line| {
+            let (from, to) = line.split_once(": ").unwrap();
+            let from = intern(&mut graph, from);
+            to.split_whitespace().for_each(|to| {
+                let to = intern(&mut graph, to);
+                let edge = graph.edges.len();
+                graph.edges.push((from, to));
+                graph.adjacent[from].push((to, edge));
+                graph.adjacent[to].push((from, edge));
+            });
+        });
+
+        graph
+    }
+
+    /// Find a minimum cut between `source` and `sink` using Edmonds-Karp, as long as it has at
+    /// most `max_cut` edges.
+    ///
+    /// Returns the vertices on the side of `source`.
+    fn min_cut(&self, source: usize, sink: usize, max_cut: usize) -> Option<Vec<bool>> {
+        // flow along the edge in the direction it was parsed in, -1 means the other direction
+        let mut flow = vec![0i8; self.edges.len()];
+        let residual = |flow: &[i8], from: usize, edge: usize| -> bool {
+            if self.edges[edge].0 == from {
+                flow[edge] < 1
+            } else {
+                flow[edge] > -1
+            }
+        };
+
+        let mut prev: Vec<Option<(usize, usize)>> = vec![None; self.names.len()];
+        let mut reached = vec![false; self.names.len()];
+        let mut queue = VecDeque::new();
+
+        for _ in 0..=max_cut {
+            // find the shortest augmenting path with breadth-first search
+            prev.fill(None);
+            reached.fill(false);
+            reached[source] = true;
+            queue.clear();
+            queue.push_back(source);
+
+            while let Some(vertex) = queue.pop_front() {
+                for &(next, edge) in &self.adjacent[vertex] {
+                    if !reached[next] && residual(&flow, vertex, edge) {
+                        reached[next] = true;
+                        prev[next] = Some((vertex, edge));
+                        queue.push_back(next);
+                    }
+                }
+            }
+
+            if !reached[sink] {
+                // no augmenting path left, everything reachable is on the source side
+                return Some(reached);
+            }
+
+            // push one unit of flow along the path
+            let mut vertex = sink;
+            while let Some((from, edge)) = prev[vertex] {
+                flow[edge] += if self.edges[edge].0 == from { 1 } else { -1 };
+                vertex = from;
+            }
+        }
+
+        // the flow exceeds `max_cut`, so both vertices are on the same side
+        None
+    }
+}
+
+fn main() {
+    let mut challenge = advent_of_code_2023::Challenge::start(25, 1);
+
+    let graph = Graph::parse(challenge.input_lines());
+
+    challenge.finish_parsing();
+
+    // the vertices on the other side of the three wires are exactly
+    // the ones that can't push more than three units of flow to vertex 0
+    let source_side = (1..graph.names.len())
+        .find_map(|sink| graph.min_cut(0, sink, 3))
+        .expect("no cut with at most three wires");
+
+    let cut = graph
+        .edges
+        .iter()
+        .filter(|&&(from, to)| source_side[from] != source_side[to])
+        .map(|&(from, to)| format!("{}/{}", graph.names[from], graph.names[to]))
+        .collect::<Vec<_>>();
+    println!("[i] Cut: {}", cut.join(", "));
+
+    let source_size = source_side.iter().filter(|&&side| side).count();
+    let solution = source_size * (graph.names.len() - source_size);
+
+    challenge.finish(solution);
+}
+
+#[cfg(test)]
+mod cut_test {
+    use super::Graph;
+
+    const EXAMPLE: &str = "jqt: rhn xhk nvd
+rsh: frs pzl lsr
+xhk: hfx
+cmg: qnr nvd lhk bvb
+rhn: xhk bvb hfx
+bvb: xhk hfx
+pzl: lsr hfx nvd
+qnr: nvd
+ntq: jqt hfx bvb xhk
+nvd: lhk
+lsr: lhk
+rzs: qnr cmg lsr rsh
+frs: qnr lhk lsr";
+
+    #[test]
+    fn example() {
+        let graph = Graph::parse(EXAMPLE.lines());
+        let source_side = (1..graph.names.len())
+            .find_map(|sink| graph.min_cut(0, sink, 3))
+            .unwrap();
+
+        let mut cut = graph
+            .edges
+            .iter()
+            .filter(|&&(from, to)| source_side[from] != source_side[to])
+            .map(|&(from, to)| {
+                let (from, to) = (graph.names[from], graph.names[to]);
+                format!("{}/{}", from.min(to), from.max(to))
+            })
+            .collect::<Vec<_>>();
+        cut.sort_unstable();
+        assert_eq!(cut, ["bvb/cmg", "hfx/pzl", "jqt/nvd"]);
+
+        let source_size = source_side.iter().filter(|&&side| side).count();
+        assert_eq!(source_size * (graph.names.len() - source_size), 54);
+    }
+}
@@ -0,0 +1,160 @@
+//! Find where a sequence `x, f(x), f(f(x)), ...` starts repeating.
+//!
+//! States are always compared with `Eq`, hashes are only used to find candidates.
+
+use smallvec::SmallVec;
+use std::{
+    collections::HashMap,
+    hash::{BuildHasher, Hash, RandomState},
+};
+
+/// The sequence repeats every `len` steps once `prefix` steps are done.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cycle {
+    pub prefix: usize,
+    pub len: usize,
+}
+
+impl Cycle {
+    /// Brent's algorithm, only keeps two states around.
+    ///
+    /// Calls `step` up to three times as often as [`History::record`].
+    pub fn detect<T: Clone + Eq>(start: &T, mut step: impl FnMut(&T) -> T) -> Cycle {
+        // find the cycle length by moving the tortoise to the hare at every power of two
+        let (mut power, mut len) = (1, 1);
+        let mut tortoise = start.clone();
+        let mut hare = step(start);
+        while tortoise != hare {
+            if power == len {
+                tortoise = hare.clone();
+                power *= 2;
+                len = 0;
+            }
+            hare = step(&hare);
+            len += 1;
+        }
+
+        // with the hare `len` steps ahead both meet at the start of the cycle
+        let mut tortoise = start.clone();
+        let mut hare = start.clone();
+        (0..len).for_each(|_| hare = step(&hare));
+        let mut prefix = 0;
+        while tortoise != hare {
+            tortoise = step(&tortoise);
+            hare = step(&hare);
+            prefix += 1;
+        }
+
+        Cycle { prefix, len }
+    }
+
+    /// Index of the state after `n` steps within the first `prefix + len` states.
+    pub fn reduce(&self, n: usize) -> usize {
+        match n.checked_sub(self.prefix) {
+            Some(offset) => self.prefix + offset % self.len,
+            None => n,
+        }
+    }
+}
+
+/// Every state of a sequence up to its first repetition.
+#[derive(Debug, Clone)]
+pub struct History<T> {
+    /// The first `prefix + len` states, starting with the initial state
+    pub states: Vec<T>,
+    pub cycle: Cycle,
+}
+
+impl<T: Hash + Eq> History<T> {
+    /// Step from `start` until a state repeats, calling `step` once per distinct state.
+    pub fn record(start: T, mut step: impl FnMut(&T) -> T) -> History<T> {
+        let hasher = RandomState::new();
+        // state indices by hash, states with colliding hashes share an entry
+        let mut seen: HashMap<u64, SmallVec<[usize; 1]>> = HashMap::new();
+
+        let mut states = Vec::new();
+        let mut state = start;
+        loop {
+            let candidates = seen.entry(hasher.hash_one(&state)).or_default();
+            if let Some(&prefix) = candidates.iter().find(|&&idx| states[idx] == state) {
+                let len = states.len() - prefix;
+                return History {
+                    states,
+                    cycle: Cycle { prefix, len },
+                };
+            }
+            candidates.push(states.len());
+
+            let next = step(&state);
+            states.push(state);
+            state = next;
+        }
+    }
+
+    /// The state after `n` steps.
+    pub fn nth(&self, n: usize) -> &T {
+        &self.states[self.cycle.reduce(n)]
+    }
+}
+
+#[cfg(test)]
+mod cycle_test {
+    use super::{Cycle, History};
+
+    /// `x -> x² + 1 (mod m)` starting at `x`
+    fn step(m: u64) -> impl Fn(&u64) -> u64 {
+        move |&x| (x * x + 1) % m
+    }
+
+    fn brute_force(start: u64, m: u64) -> Cycle {
+        let mut states = vec![start];
+        loop {
+            let next = step(m)(states.last().unwrap());
+            if let Some(prefix) = states.iter().position(|&x| x == next) {
+                return Cycle {
+                    prefix,
+                    len: states.len() - prefix,
+                };
+            }
+            states.push(next);
+        }
+    }
+
+    #[test]
+    fn against_brute_force() {
+        for m in 1..60 {
+            for start in 0..m {
+                let expected = brute_force(start, m);
+                assert_eq!(Cycle::detect(&start, step(m)), expected);
+                assert_eq!(History::record(start, step(m)).cycle, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn nth() {
+        let history = History::record(3, step(1000));
+        let mut x = 3;
+        for n in 0..200 {
+            assert_eq!(*history.nth(n), x);
+            x = step(1000)(&x);
+        }
+    }
+
+    #[test]
+    fn colliding_hashes() {
+        /// A state whose hash ignores everything but the parity.
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        struct Parity(u64);
+
+        impl std::hash::Hash for Parity {
+            fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+                (self.0 % 2).hash(state);
+            }
+        }
+
+        let history = History::record(Parity(0), |&Parity(x)| Parity((x + 2) % 10));
+        assert_eq!(history.cycle, Cycle { prefix: 0, len: 5 });
+        assert_eq!(*history.nth(7), Parity(4));
+    }
+}
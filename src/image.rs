@@ -0,0 +1,241 @@
+//! Export grids as PPM or PNG images without pulling in an image crate.
+//!
+//! The PNG encoder only emits uncompressed deflate blocks, which keeps it tiny
+//! while still producing files every viewer understands.
+
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+/// The path passed as `--image=<path>` on the command line, if any.
+pub fn path_from_args() -> Option<PathBuf> {
+    std::env::args()
+        .skip(1)
+        .find_map(|arg| arg.strip_prefix("--image=").map(PathBuf::from))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rgb(pub u8, pub u8, pub u8);
+
+impl Rgb {
+    pub const BLACK: Rgb = Rgb(0, 0, 0);
+    pub const WHITE: Rgb = Rgb(255, 255, 255);
+    pub const GRAY: Rgb = Rgb(128, 128, 128);
+    pub const RED: Rgb = Rgb(220, 40, 40);
+    pub const GREEN: Rgb = Rgb(40, 200, 60);
+    pub const BLUE: Rgb = Rgb(40, 80, 220);
+    pub const YELLOW: Rgb = Rgb(240, 210, 40);
+
+    /// Map `value` in `min..=max` onto a dark-blue to yellow color ramp.
+    pub fn heat(value: u64, min: u64, max: u64) -> Rgb {
+        let t = match max.saturating_sub(min) {
+            0 => 0.0,
+            span => (value.clamp(min, max) - min) as f64 / span as f64,
+        };
+        let lerp = |from: u8, to: u8| (from as f64 + (to as f64 - from as f64) * t).round() as u8;
+        Rgb(lerp(20, 250), lerp(20, 230), lerp(90, 40))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Image {
+    width: usize,
+    height: usize,
+    pixels: Vec<Rgb>,
+}
+
+impl Image {
+    pub fn new(width: usize, height: usize, background: Rgb) -> Image {
+        Image {
+            width,
+            height,
+            pixels: vec![background; width * height],
+        }
+    }
+
+    /// Create an image with one pixel per grid cell.
+    pub fn from_grid<T>(grid: &[T], width: usize, color: impl Fn(&T) -> Rgb) -> Image {
+        assert_eq!(grid.len() % width, 0, "grid is not rectangular");
+        Image {
+            width,
+            height: grid.len() / width,
+            pixels: grid.iter().map(color).collect(),
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn set(&mut self, idx: usize, color: Rgb) {
+        self.pixels[idx] = color;
+    }
+
+    /// Color all cells, e.g. the tiles of a path, with the same color.
+    pub fn overlay(&mut self, cells: &[usize], color: Rgb) {
+        cells.iter().for_each(|&idx| self.pixels[idx] = color);
+    }
+
+    /// Blow up every pixel into a `factor`x`factor` square.
+    pub fn scaled(&self, factor: usize) -> Image {
+        let width = self.width * factor;
+        let mut pixels = Vec::with_capacity(self.pixels.len() * factor * factor);
+        self.pixels.chunks(self.width).for_each(|row| {
+            let start = pixels.len();
+            row.iter()
+                .for_each(|&px| pixels.extend(std::iter::repeat_n(px, factor)));
+            (1..factor).for_each(|_| pixels.extend_from_within(start..start + width));
+        });
+        Image {
+            width,
+            height: self.height * factor,
+            pixels,
+        }
+    }
+
+    /// Write the image as binary PPM (`P6`).
+    pub fn write_ppm(&self, mut w: impl Write) -> io::Result<()> {
+        write!(w, "P6\n{} {}\n255\n", self.width, self.height)?;
+        self.pixels
+            .iter()
+            .try_for_each(|&Rgb(r, g, b)| w.write_all(&[r, g, b]))
+    }
+
+    /// Write the image as 8-bit RGB PNG.
+    pub fn write_png(&self, mut w: impl Write) -> io::Result<()> {
+        w.write_all(b"\x89PNG\r\n\x1a\n")?;
+
+        let mut header = Vec::with_capacity(13);
+        header.extend_from_slice(&(self.width as u32).to_be_bytes());
+        header.extend_from_slice(&(self.height as u32).to_be_bytes());
+        // bit depth 8, color type RGB, deflate, default filters, no interlacing
+        header.extend_from_slice(&[8, 2, 0, 0, 0]);
+        write_png_chunk(&mut w, b"IHDR", &header)?;
+
+        // every scanline starts with its filter type, 0 means unfiltered
+        let mut raw = Vec::with_capacity(self.height * (1 + 3 * self.width));
+        self.pixels.chunks(self.width).for_each(|row| {
+            raw.push(0);
+            row.iter()
+                .for_each(|&Rgb(r, g, b)| raw.extend_from_slice(&[r, g, b]));
+        });
+        write_png_chunk(&mut w, b"IDAT", &zlib_stored(&raw))?;
+
+        write_png_chunk(&mut w, b"IEND", &[])
+    }
+
+    /// Save the image, the format is chosen by the file extension (`.ppm` or `.png`).
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        // check the format first, so an existing file is never truncated for nothing
+        let png = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("ppm") => false,
+            Some("png") => true,
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("unsupported image format: {}", path.display()),
+                ))
+            }
+        };
+
+        let mut w = BufWriter::new(File::create(path)?);
+        if png {
+            self.write_png(&mut w)?;
+        } else {
+            self.write_ppm(&mut w)?;
+        }
+        w.flush()
+    }
+}
+
+fn write_png_chunk(mut w: impl Write, kind: &[u8; 4], data: &[u8]) -> io::Result<()> {
+    w.write_all(&(data.len() as u32).to_be_bytes())?;
+    w.write_all(kind)?;
+    w.write_all(data)?;
+    let crc = crc32(crc32(!0, kind), data);
+    w.write_all(&(!crc).to_be_bytes())
+}
+
+/// Wrap `data` into a zlib stream made of uncompressed deflate blocks.
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    const MAX_BLOCK: usize = u16::MAX as usize;
+
+    let mut out = Vec::with_capacity(data.len() + data.len() / MAX_BLOCK * 5 + 11);
+    // deflate with a 32K window, no preset dictionary, check bits make it divisible by 31
+    out.extend_from_slice(&[0x78, 0x01]);
+
+    let mut blocks = data.chunks(MAX_BLOCK).peekable();
+    if blocks.peek().is_none() {
+        // a stream needs at least one (final) block
+        out.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        let is_final = blocks.peek().is_none();
+        let len = block.len() as u16;
+        out.push(u8::from(is_final));
+        out.extend_from_slice(&len.to_le_bytes());
+        out.extend_from_slice(&(!len).to_le_bytes());
+        out.extend_from_slice(block);
+    }
+
+    out.extend_from_slice(&adler32(data).to_be_bytes());
+    out
+}
+
+/// Continue a CRC-32 (as used by PNG), start with `!0` and invert the result.
+fn crc32(mut crc: u32, data: &[u8]) -> u32 {
+    data.iter().for_each(|&b| {
+        crc ^= u32::from(b);
+        (0..8).for_each(|_| crc = (crc >> 1) ^ (0xedb8_8320 & (crc & 1).wrapping_neg()));
+    });
+    crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    const MOD: u32 = 65521;
+    let (a, b) = data.iter().fold((1u32, 0u32), |(a, b), &byte| {
+        let a = (a + u32::from(byte)) % MOD;
+        (a, (b + a) % MOD)
+    });
+    (b << 16) | a
+}
+
+#[cfg(test)]
+mod image_test {
+    use super::{adler32, crc32, Image, Rgb};
+    use std::io::ErrorKind;
+
+    #[test]
+    fn checksums() {
+        assert_eq!(!crc32(!0, b"123456789"), 0xcbf4_3926);
+        assert_eq!(adler32(b"Wikipedia"), 0x11e6_0398);
+    }
+
+    #[test]
+    fn scaled() {
+        let image = Image::from_grid(&[0u8, 1, 2, 3], 2, |&v| Rgb(v, v, v));
+        let scaled = image.scaled(2);
+        assert_eq!((scaled.width(), scaled.height()), (4, 4));
+        let values = scaled.pixels.iter().map(|px| px.0).collect::<Vec<_>>();
+        assert_eq!(values, [0, 0, 1, 1, 0, 0, 1, 1, 2, 2, 3, 3, 2, 2, 3, 3]);
+    }
+
+    #[test]
+    fn unsupported_format() {
+        let path = std::env::temp_dir().join(format!("image-test-{}.txt", std::process::id()));
+        std::fs::write(&path, "notes").unwrap();
+
+        let image = Image::from_grid(&[0u8], 1, |&v| Rgb(v, v, v));
+        let err = image.save(&path).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+        // the file is left untouched
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "notes");
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,315 @@
+//! Sets of values stored as sorted lists of disjoint half-open ranges.
+
+use smallvec::{smallvec, SmallVec};
+use std::{fmt::Debug, ops::Range};
+
+pub trait Set: Sized {
+    /// Set intersection of self with rhs, this operation is commutative.
+    fn intersect(&self, rhs: &Self) -> Option<Self>;
+
+    /// Subtract rhs from self.
+    fn subtract(&self, rhs: &Self) -> SmallVec<[Self; 2]>;
+}
+
+impl<T: Copy + Ord + Debug> Set for Range<T> {
+    fn intersect(&self, other: &Self) -> Option<Self> {
+        let start = self.start.max(other.start);
+        let end = self.end.min(other.end);
+
+        if start >= end {
+            None
+        } else {
+            Some(start..end)
+        }
+    }
+    fn subtract(&self, rhs: &Self) -> SmallVec<[Self; 2]> {
+        let Some(intersection) = self.intersect(rhs) else {
+            // the sets don't overlap
+            return smallvec![self.clone()];
+        };
+
+        if &intersection == self {
+            // rhs fully covers self
+            return smallvec![];
+        }
+
+        if rhs.start > self.start && rhs.end < self.end {
+            // rhs is fully contained in self
+            smallvec![self.start..rhs.start, rhs.end..self.end]
+        } else if rhs.end > self.start && rhs.start <= self.start {
+            // rhs overlaps self on the left side
+            smallvec![rhs.end..self.end]
+        } else if rhs.start < self.end && rhs.end >= self.end {
+            // rhs overlaps self on the right side
+            smallvec![self.start..rhs.start]
+        } else {
+            unreachable!(
+                "all cases should have been covered by the code above\n\
+                    \tself: {:?}\n\
+                    \trhs: {:?}\n\
+                    \tintersection: {:?}",
+                self, rhs, intersection
+            );
+        }
+    }
+}
+
+/// A set of values made of disjoint ranges.
+///
+/// The ranges are kept sorted, non-empty and never touch each other,
+/// so two sets with the same values always have the same ranges.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct IntervalSet<T> {
+    ranges: Vec<Range<T>>,
+}
+
+impl<T> Default for IntervalSet<T> {
+    fn default() -> Self {
+        IntervalSet { ranges: Vec::new() }
+    }
+}
+
+impl<T: Copy + Ord + Debug> From<Range<T>> for IntervalSet<T> {
+    fn from(range: Range<T>) -> Self {
+        IntervalSet::from_ranges([range])
+    }
+}
+
+impl<T: Copy + Ord + Debug> FromIterator<Range<T>> for IntervalSet<T> {
+    fn from_iter<I: IntoIterator<Item = Range<T>>>(iter: I) -> Self {
+        IntervalSet::from_ranges(iter)
+    }
+}
+
+impl<T: Copy + Ord + Debug> IntervalSet<T> {
+    pub fn new() -> IntervalSet<T> {
+        IntervalSet::default()
+    }
+
+    /// Build a set from arbitrary, possibly overlapping or empty, ranges.
+    pub fn from_ranges(ranges: impl IntoIterator<Item = Range<T>>) -> IntervalSet<T> {
+        let mut ranges = ranges
+            .into_iter()
+            .filter(|range| range.start < range.end)
+            .collect::<Vec<_>>();
+        ranges.sort_unstable_by_key(|range| range.start);
+
+        // merge overlapping and touching ranges
+        let mut merged: Vec<Range<T>> = Vec::with_capacity(ranges.len());
+        for range in ranges {
+            match merged.last_mut() {
+                Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+                _ => merged.push(range),
+            }
+        }
+
+        IntervalSet { ranges: merged }
+    }
+
+    /// The disjoint ranges of this set in ascending order.
+    pub fn ranges(&self) -> &[Range<T>] {
+        &self.ranges
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    pub fn min(&self) -> Option<T> {
+        self.ranges.first().map(|range| range.start)
+    }
+
+    pub fn contains(&self, value: T) -> bool {
+        let idx = self.ranges.partition_point(|range| range.end <= value);
+        self.ranges
+            .get(idx)
+            .is_some_and(|range| range.start <= value)
+    }
+
+    pub fn insert(&mut self, range: Range<T>) {
+        *self = self.union(&IntervalSet::from(range));
+    }
+
+    pub fn union(&self, rhs: &Self) -> Self {
+        IntervalSet::from_ranges(self.ranges.iter().chain(&rhs.ranges).cloned())
+    }
+
+    pub fn intersection(&self, rhs: &Self) -> Self {
+        let (lhs, rhs) = (&self.ranges, &rhs.ranges);
+        let mut ranges = Vec::new();
+        let (mut i, mut j) = (0, 0);
+
+        while i < lhs.len() && j < rhs.len() {
+            if let Some(intersection) = lhs[i].intersect(&rhs[j]) {
+                ranges.push(intersection);
+            }
+            // advance whichever range ends first, it can't overlap anything else
+            if lhs[i].end < rhs[j].end {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+
+        IntervalSet { ranges }
+    }
+
+    /// All values in self that are not in rhs.
+    pub fn difference(&self, rhs: &Self) -> Self {
+        let rhs = &rhs.ranges;
+        let mut ranges = Vec::new();
+        let mut first = 0;
+
+        for range in &self.ranges {
+            // skip everything that ends before this range
+            while first < rhs.len() && rhs[first].end <= range.start {
+                first += 1;
+            }
+
+            // cut out every overlapping range of rhs
+            let mut start = range.start;
+            for cut in rhs[first..].iter().take_while(|cut| cut.start < range.end) {
+                if cut.start > start {
+                    ranges.push(start..cut.start);
+                }
+                start = start.max(cut.end);
+            }
+            if start < range.end {
+                ranges.push(start..range.end);
+            }
+        }
+
+        IntervalSet { ranges }
+    }
+
+    /// All values within `bounds` that are not in self.
+    pub fn complement(&self, bounds: Range<T>) -> Self {
+        IntervalSet::from(bounds).difference(self)
+    }
+
+    /// Number of values in the set.
+    pub fn len(&self) -> u64
+    where
+        T: Into<u64>,
+    {
+        self.ranges
+            .iter()
+            .map(|range| range.end.into() - range.start.into())
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod set_test {
+    use super::{Range, Set};
+    use smallvec::{smallvec, SmallVec};
+
+    #[test]
+    fn intersect() {
+        macro_rules! test_eq {
+            ($lhs:expr, $rhs:expr => []) => {
+                assert_eq!(($lhs).intersect(&($rhs)), None);
+                assert_eq!(($rhs).intersect(&($lhs)), None);
+            };
+            ($lhs:expr, $rhs:expr => [$result:expr]) => {
+                assert_eq!(($lhs).intersect(&($rhs)), Some($result));
+                assert_eq!(($rhs).intersect(&($lhs)), Some($result));
+            };
+        }
+
+        test_eq!(0..10, 2..12 => [2..10]);
+        test_eq!(10..20, 0..10 => []);
+        test_eq!(0..1, 10..11 => []);
+    }
+
+    #[test]
+    fn subtract() {
+        macro_rules! test_eq {
+            ($base:expr, $sub:expr => [$($equals:expr),*]) => {{
+                let expected: SmallVec::<[Range<u64>; 2]> = smallvec![$($equals),*];
+                assert_eq!(($base).subtract(&($sub)), expected);
+            }};
+        }
+
+        test_eq!(1..6, 10..16 => [1..6]);
+        test_eq!(1..6, 0..3 => [3..6]);
+        test_eq!(1..6, 4..7 => [1..4]);
+        test_eq!(1..6, 3..5 => [1..3, 5..6]);
+        test_eq!(1..6, 1..6 => []);
+        test_eq!(1..6, 1..1 => [1..6]);
+        test_eq!(1..6, 1..2 => [2..6]);
+        test_eq!(1..6, 5..6 => [1..5]);
+        test_eq!(1..6, 6..6 => [1..6]);
+    }
+}
+
+#[cfg(test)]
+mod interval_set_test {
+    use super::IntervalSet;
+
+    fn set(ranges: &[(u64, u64)]) -> IntervalSet<u64> {
+        ranges.iter().map(|&(start, end)| start..end).collect()
+    }
+
+    #[test]
+    fn normalize() {
+        assert_eq!(
+            set(&[(5, 8), (0, 2), (1, 3), (3, 4), (9, 9)]),
+            set(&[(0, 4), (5, 8)])
+        );
+        assert_eq!(set(&[(0, 4), (5, 8)]).ranges(), [0..4, 5..8]);
+        assert!(set(&[(3, 3)]).is_empty());
+    }
+
+    #[test]
+    fn operations() {
+        let lhs = set(&[(0, 10), (20, 30)]);
+        let rhs = set(&[(5, 25), (28, 40)]);
+
+        assert_eq!(lhs.union(&rhs), set(&[(0, 40)]));
+        assert_eq!(lhs.intersection(&rhs), set(&[(5, 10), (20, 25), (28, 30)]));
+        assert_eq!(lhs.difference(&rhs), set(&[(0, 5), (25, 28)]));
+        assert_eq!(rhs.difference(&lhs), set(&[(10, 20), (30, 40)]));
+        assert_eq!(lhs.complement(0..50), set(&[(10, 20), (30, 50)]));
+        assert_eq!(lhs.len(), 20);
+    }
+
+    #[test]
+    fn contains() {
+        let lhs = set(&[(0, 10), (20, 30)]);
+        assert!(lhs.contains(0) && lhs.contains(9) && lhs.contains(20));
+        assert!(!lhs.contains(10) && !lhs.contains(19) && !lhs.contains(30));
+    }
+
+    #[test]
+    fn against_brute_force() {
+        let sets = [
+            set(&[]),
+            set(&[(0, 3)]),
+            set(&[(1, 2), (4, 7)]),
+            set(&[(0, 1), (2, 3), (5, 6), (7, 8)]),
+            set(&[(3, 8)]),
+        ];
+        let values =
+            |set: &IntervalSet<u64>| (0..10).filter(|&v| set.contains(v)).collect::<Vec<_>>();
+
+        for lhs in &sets {
+            for rhs in &sets {
+                let (l, r) = (values(lhs), values(rhs));
+                let union = (0..10)
+                    .filter(|v| l.contains(v) || r.contains(v))
+                    .collect::<Vec<_>>();
+                let inter = (0..10)
+                    .filter(|v| l.contains(v) && r.contains(v))
+                    .collect::<Vec<_>>();
+                let diff = (0..10)
+                    .filter(|v| l.contains(v) && !r.contains(v))
+                    .collect::<Vec<_>>();
+
+                assert_eq!(values(&lhs.union(rhs)), union);
+                assert_eq!(values(&lhs.intersection(rhs)), inter);
+                assert_eq!(values(&lhs.difference(rhs)), diff);
+            }
+        }
+    }
+}
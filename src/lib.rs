@@ -1,3 +1,9 @@
+pub mod animation;
+pub mod cycle;
+pub mod image;
+pub mod interval_set;
+pub mod polygon;
+
 use std::{
     fmt::{Debug, Display},
     path::PathBuf,
@@ -50,7 +56,7 @@ pub struct Challenge {
 impl Challenge {
     /// Start the challenge by loading the input and recording the current time.
     pub fn start(day: usize, part: usize) -> Challenge {
-        assert!((1..=24).contains(&day), "day {} is out of range", day);
+        assert!((1..=25).contains(&day), "day {} is out of range", day);
         assert!((1..=2).contains(&part), "part {} is out of range", part);
 
         // load the puzzle input
@@ -0,0 +1,184 @@
+//! Simple polygons on integer coordinates.
+//!
+//! A polygon is a list of `(x, y)` vertices, the last vertex connects back to the first.
+//! Repeating the first vertex at the end is allowed. All arithmetic happens on `i128` and
+//! panics instead of overflowing.
+
+use num_integer::Integer;
+
+pub type Point = (i64, i64);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Orientation {
+    /// Counter-clockwise with the y axis pointing up.
+    Positive,
+    /// Clockwise with the y axis pointing up.
+    Negative,
+    /// The polygon has no area.
+    Degenerate,
+}
+
+/// Where a point lies relative to a polygon.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Location {
+    Inside,
+    Boundary,
+    Outside,
+}
+
+fn add(lhs: i128, rhs: i128) -> i128 {
+    lhs.checked_add(rhs).expect("polygon arithmetic overflowed")
+}
+
+fn sub(lhs: i128, rhs: i128) -> i128 {
+    lhs.checked_sub(rhs).expect("polygon arithmetic overflowed")
+}
+
+fn mul(lhs: i128, rhs: i128) -> i128 {
+    lhs.checked_mul(rhs).expect("polygon arithmetic overflowed")
+}
+
+/// Every edge as a pair of its end points.
+fn edges(vertices: &[Point]) -> impl Iterator<Item = (Point, Point)> + '_ {
+    (0..vertices.len()).map(|idx| (vertices[idx], vertices[(idx + 1) % vertices.len()]))
+}
+
+/// Cross product of `b - a` and `p - a`, positive if `p` lies left of the line from `a` to `b`.
+fn cross(a: Point, b: Point, p: Point) -> i128 {
+    let (ax, ay) = (a.0 as i128, a.1 as i128);
+    sub(
+        mul(sub(b.0 as i128, ax), sub(p.1 as i128, ay)),
+        mul(sub(b.1 as i128, ay), sub(p.0 as i128, ax)),
+    )
+}
+
+/// Twice the signed area, positive for counter-clockwise polygons.
+///
+/// Doubling keeps the result an integer.
+pub fn double_signed_area(vertices: &[Point]) -> i128 {
+    // https://en.wikipedia.org/wiki/Shoelace_formula
+    edges(vertices).fold(0, |acc, ((x_1, y_1), (x_2, y_2))| {
+        let term = sub(mul(x_1 as i128, y_2 as i128), mul(x_2 as i128, y_1 as i128));
+        add(acc, term)
+    })
+}
+
+pub fn orientation(vertices: &[Point]) -> Orientation {
+    match double_signed_area(vertices).signum() {
+        1 => Orientation::Positive,
+        -1 => Orientation::Negative,
+        _ => Orientation::Degenerate,
+    }
+}
+
+/// Euclidean length of the boundary.
+pub fn perimeter(vertices: &[Point]) -> f64 {
+    edges(vertices)
+        .map(|((x_1, y_1), (x_2, y_2))| {
+            let dx = sub(x_2 as i128, x_1 as i128) as f64;
+            let dy = sub(y_2 as i128, y_1 as i128) as f64;
+            dx.hypot(dy)
+        })
+        .sum()
+}
+
+/// Number of lattice points on the boundary.
+///
+/// This equals the perimeter if all edges are horizontal or vertical.
+pub fn boundary_points(vertices: &[Point]) -> i128 {
+    edges(vertices).fold(0, |acc, ((x_1, y_1), (x_2, y_2))| {
+        let dx = sub(x_2 as i128, x_1 as i128);
+        let dy = sub(y_2 as i128, y_1 as i128);
+        add(acc, dx.gcd(&dy))
+    })
+}
+
+/// Number of lattice points strictly inside the polygon.
+pub fn interior_points(vertices: &[Point]) -> i128 {
+    // https://en.wikipedia.org/wiki/Pick%27s_theorem
+    let double_area = double_signed_area(vertices).abs();
+    sub(double_area, boundary_points(vertices)) / 2 + 1
+}
+
+pub fn locate(vertices: &[Point], point: Point) -> Location {
+    let mut inside = false;
+    for (a, b) in edges(vertices) {
+        let cross = cross(a, b, point);
+        let on_segment = (a.0.min(b.0)..=a.0.max(b.0)).contains(&point.0)
+            && (a.1.min(b.1)..=a.1.max(b.1)).contains(&point.1);
+        if cross == 0 && on_segment {
+            return Location::Boundary;
+        }
+
+        // count the edges crossing the ray going right from the point
+        if (a.1 > point.1) != (b.1 > point.1) && (cross > 0) == (b.1 > a.1) {
+            inside = !inside;
+        }
+    }
+
+    if inside {
+        Location::Inside
+    } else {
+        Location::Outside
+    }
+}
+
+#[cfg(test)]
+mod polygon_test {
+    use super::*;
+
+    const SQUARE: [Point; 4] = [(0, 0), (4, 0), (4, 4), (0, 4)];
+    const TRIANGLE: [Point; 3] = [(0, 0), (0, 3), (4, 0)];
+    /// An L shape, listed clockwise and closed by repeating the first vertex.
+    const ELL: [Point; 7] = [(0, 0), (0, 5), (2, 5), (2, 2), (6, 2), (6, 0), (0, 0)];
+
+    #[test]
+    fn measures() {
+        assert_eq!(double_signed_area(&SQUARE), 32);
+        assert_eq!(perimeter(&SQUARE), 16.0);
+        assert_eq!(boundary_points(&SQUARE), 16);
+        assert_eq!(interior_points(&SQUARE), 9);
+        assert_eq!(orientation(&SQUARE), Orientation::Positive);
+
+        assert_eq!(double_signed_area(&TRIANGLE), -12);
+        assert_eq!(perimeter(&TRIANGLE), 12.0);
+        assert_eq!(boundary_points(&TRIANGLE), 8);
+        assert_eq!(interior_points(&TRIANGLE), 3);
+        assert_eq!(orientation(&TRIANGLE), Orientation::Negative);
+
+        assert_eq!(double_signed_area(&ELL), -36);
+        assert_eq!(perimeter(&ELL), 22.0);
+        assert_eq!(boundary_points(&ELL), 22);
+        assert_eq!(
+            orientation(&[(0, 0), (2, 2), (4, 4)]),
+            Orientation::Degenerate
+        );
+    }
+
+    #[test]
+    fn locate_against_pick() {
+        for vertices in [&SQUARE[..], &TRIANGLE, &ELL] {
+            let count = |location| {
+                (-1..8)
+                    .flat_map(|x| (-1..8).map(move |y| (x, y)))
+                    .filter(|&point| locate(vertices, point) == location)
+                    .count() as i128
+            };
+            assert_eq!(count(Location::Inside), interior_points(vertices));
+            assert_eq!(count(Location::Boundary), boundary_points(vertices));
+        }
+        assert_eq!(locate(&SQUARE, (2, 2)), Location::Inside);
+        assert_eq!(locate(&SQUARE, (4, 1)), Location::Boundary);
+        assert_eq!(locate(&SQUARE, (5, 2)), Location::Outside);
+    }
+
+    #[test]
+    #[should_panic(expected = "overflowed")]
+    fn overflow() {
+        double_signed_area(&[
+            (i64::MIN, i64::MIN),
+            (i64::MAX, i64::MIN),
+            (i64::MAX, i64::MAX),
+        ]);
+    }
+}